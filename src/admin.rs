@@ -0,0 +1,83 @@
+use mongodb::bson::doc;
+use mongodb::error::{CommandError, ErrorKind};
+use mongodb::Client;
+
+use crate::idempotency::IdempotencyStore;
+use crate::storage::PersonStore;
+use crate::{migrations, outbox, storage, webhooks};
+
+/// Every collection the application writes to, besides the persons one.
+pub const COLLECTIONS: [&str; 6] = [
+    migrations::MIGRATIONS_COLLECTION,
+    "idempotency_keys",
+    webhooks::WEBHOOKS_COLLECTION,
+    webhooks::DELIVERIES_COLLECTION,
+    webhooks::DELIVERY_LOGS_COLLECTION,
+    webhooks::DEAD_LETTERS_COLLECTION,
+];
+
+/// The code MongoDB reports creating an existing collection with.
+const NAMESPACE_EXISTS_CODE: i32 = 48;
+
+/// Prefix of the databases `tests/api/helpers.rs` creates, followed by a ULID.
+const TEST_DATABASE_PREFIX: &str = "test-";
+
+/// Creates the collections and indexes the application relies on and upgrades the stored persons,
+/// returning the migrations that ran. Safe to run repeatedly.
+pub async fn migrate(
+    client: &PersonStore,
+    idempotency: &IdempotencyStore,
+) -> Result<Vec<&'static str>, mongodb::error::Error> {
+    for collection in [client.collection_name()].into_iter().chain(COLLECTIONS) {
+        match client.database().create_collection(collection, None).await {
+            Err(error)
+                if matches!(
+                    &*error.kind,
+                    ErrorKind::Command(CommandError {
+                        code: NAMESPACE_EXISTS_CODE,
+                        ..
+                    })
+                ) => {}
+            result => result?,
+        }
+    }
+    storage::ensure_person_indexes(client).await?;
+    outbox::ensure_index(client).await?;
+    idempotency.ensure_index().await?;
+    webhooks::ensure_index(client.database()).await?;
+    migrations::run(client).await
+}
+
+/// Whether `name` looks like a database created by the API tests: `test-` and a ULID.
+pub fn is_test_database_name(name: &str) -> bool {
+    name.strip_prefix(TEST_DATABASE_PREFIX).is_some_and(|ulid| {
+        ulid.len() == 26
+            && ulid.chars().all(|character| {
+                character.is_ascii_digit()
+                    || (character.is_ascii_uppercase() && !"ILOU".contains(character))
+            })
+    })
+}
+
+/// Drops the databases left behind by the API tests, returning their names. Only lists them when
+/// `dry_run`.
+pub async fn purge_test_databases(
+    client: &Client,
+    dry_run: bool,
+) -> Result<Vec<String>, mongodb::error::Error> {
+    let names: Vec<String> = client
+        .list_database_names(
+            doc! {"name": {"$regex": format!("^{}", TEST_DATABASE_PREFIX)}},
+            None,
+        )
+        .await?
+        .into_iter()
+        .filter(|name| is_test_database_name(name))
+        .collect();
+    if !dry_run {
+        for name in &names {
+            client.database(name).drop(None).await?;
+        }
+    }
+    Ok(names)
+}
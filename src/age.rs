@@ -0,0 +1,18 @@
+use chrono::{Months, NaiveDate, Utc};
+
+pub fn today() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// Completed years between `birth_date` and `today`; someone born on February 29 turns a year
+/// older on March 1 of common years.
+pub fn age_on(birth_date: NaiveDate, today: NaiveDate) -> u32 {
+    today.years_since(birth_date).unwrap_or(0)
+}
+
+/// The latest birth date of someone at least `age` years old on `today`.
+pub fn latest_birth_date(today: NaiveDate, age: u32) -> NaiveDate {
+    age.checked_mul(12)
+        .and_then(|months| today.checked_sub_months(Months::new(months)))
+        .unwrap_or(NaiveDate::MIN)
+}
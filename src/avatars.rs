@@ -0,0 +1,165 @@
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+
+use image::{DynamicImage, ImageError, ImageFormat, ImageReader, Limits};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::blob_store::{BlobError, BlobStore};
+use crate::configuration::AvatarConfiguration;
+
+/// Thumbnails are always PNG, whatever the format of the original.
+pub const THUMBNAIL_CONTENT_TYPE: &str = "image/png";
+
+#[derive(Debug, PartialEq)]
+pub enum InvalidAvatar {
+    /// Not a PNG, JPEG or WebP image, judging by its first bytes.
+    UnsupportedFormat,
+    TooLarge,
+    Undecodable(String),
+}
+
+impl std::fmt::Display for InvalidAvatar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidAvatar::UnsupportedFormat => write!(f, "avatars must be PNG, JPEG or WebP"),
+            InvalidAvatar::TooLarge => write!(f, "avatar dimensions are too large"),
+            InvalidAvatar::Undecodable(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// An uploaded avatar, checked and resized, ready to be stored.
+pub struct ProcessedAvatar {
+    pub content_type: &'static str,
+    pub original: Vec<u8>,
+    /// One per configured size, as `(size, PNG bytes)`.
+    pub thumbnails: Vec<(u32, Vec<u8>)>,
+    /// SHA-256 of the original, in hex.
+    pub digest: String,
+}
+
+/// The format of an avatar from its magic bytes, ignoring whatever the client claimed it was.
+pub fn sniff(bytes: &[u8]) -> Result<ImageFormat, InvalidAvatar> {
+    match image::guess_format(bytes) {
+        Ok(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP)) => Ok(format),
+        _ => Err(InvalidAvatar::UnsupportedFormat),
+    }
+}
+
+/// Checks, resizes and stores avatars in a [`BlobStore`], under `avatars/<person id>/`.
+pub struct Avatars {
+    blobs: Arc<dyn BlobStore>,
+    max_bytes: usize,
+    max_dimension: u32,
+    thumbnail_sizes: Vec<u32>,
+    cache_max_age: Duration,
+}
+
+impl Avatars {
+    pub fn new(blobs: Arc<dyn BlobStore>, avatar_config: &AvatarConfiguration) -> Self {
+        Avatars {
+            blobs,
+            max_bytes: avatar_config.max_bytes,
+            max_dimension: avatar_config.max_dimension,
+            thumbnail_sizes: avatar_config.thumbnail_sizes.clone(),
+            cache_max_age: Duration::from_secs(avatar_config.cache_max_age_seconds),
+        }
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn cache_max_age(&self) -> Duration {
+        self.cache_max_age
+    }
+
+    /// Decodes an avatar and makes its thumbnails. CPU-bound, so better run off the async
+    /// workers.
+    pub fn process(&self, original: Vec<u8>) -> Result<ProcessedAvatar, InvalidAvatar> {
+        let format = sniff(&original)?;
+        let mut reader = ImageReader::with_format(Cursor::new(&original), format);
+        // Checked from the headers, before decompressing anything.
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_dimension);
+        limits.max_image_height = Some(self.max_dimension);
+        reader.limits(limits);
+        let decoded = reader.decode().map_err(|error| match error {
+            ImageError::Limits(_) => InvalidAvatar::TooLarge,
+            error => InvalidAvatar::Undecodable(error.to_string()),
+        })?;
+
+        let mut thumbnails = Vec::with_capacity(self.thumbnail_sizes.len());
+        for &size in &self.thumbnail_sizes {
+            thumbnails.push((size, encode_thumbnail(&decoded, size)?));
+        }
+        Ok(ProcessedAvatar {
+            content_type: format.to_mime_type(),
+            digest: hex::encode(Sha256::digest(&original)),
+            original,
+            thumbnails,
+        })
+    }
+
+    pub async fn store(&self, id: Uuid, avatar: ProcessedAvatar) -> Result<(), BlobError> {
+        for (size, thumbnail) in avatar.thumbnails {
+            self.blobs
+                .put(&avatar_key(id, Some(size)), thumbnail)
+                .await?;
+        }
+        self.blobs.put(&avatar_key(id, None), avatar.original).await
+    }
+
+    /// The original avatar, or its thumbnail of `size`.
+    pub async fn load(&self, id: Uuid, size: Option<u32>) -> Result<Option<Vec<u8>>, BlobError> {
+        self.blobs.get(&avatar_key(id, size)).await
+    }
+
+    /// Removes an avatar along with its thumbnails of `thumbnail_sizes`.
+    pub async fn remove(&self, id: Uuid, thumbnail_sizes: &[u32]) -> Result<(), BlobError> {
+        self.remove_thumbnails(id, thumbnail_sizes).await?;
+        self.blobs.delete(&avatar_key(id, None)).await
+    }
+
+    pub async fn remove_thumbnails(
+        &self,
+        id: Uuid,
+        thumbnail_sizes: &[u32],
+    ) -> Result<(), BlobError> {
+        for &size in thumbnail_sizes {
+            self.blobs.delete(&avatar_key(id, Some(size))).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The entity tag of the original avatar with `digest`, or of its thumbnail of `size`.
+pub fn etag(digest: &str, size: Option<u32>) -> String {
+    match size {
+        Some(size) => format!("\"{}-{}\"", digest, size),
+        None => format!("\"{}\"", digest),
+    }
+}
+
+fn avatar_key(id: Uuid, size: Option<u32>) -> String {
+    match size {
+        Some(size) => format!("avatars/{}/{}", id, size),
+        None => format!("avatars/{}/original", id),
+    }
+}
+
+/// Fits the image in a `size` square, keeping its aspect ratio and never enlarging it.
+fn encode_thumbnail(image: &DynamicImage, size: u32) -> Result<Vec<u8>, InvalidAvatar> {
+    let mut encoded = Cursor::new(Vec::new());
+    let written = if image.width() <= size && image.height() <= size {
+        image.write_to(&mut encoded, ImageFormat::Png)
+    } else {
+        image
+            .thumbnail(size, size)
+            .write_to(&mut encoded, ImageFormat::Png)
+    };
+    written.map_err(|error| InvalidAvatar::Undecodable(error.to_string()))?;
+    Ok(encoded.into_inner())
+}
@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use futures::stream::TryStreamExt;
+use mongodb::bson::doc;
+use mongodb::gridfs::GridFsBucket;
+use mongodb::options::{GridFsBucketOptions, GridFsFindOptions};
+use mongodb::Database;
+
+use crate::configuration::BlobStoreConfiguration;
+
+pub type BlobError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Somewhere binary files are kept under string keys, e.g. `avatars/<id>/original`. Putting a key
+/// again replaces its blob.
+pub trait BlobStore: Send + Sync {
+    fn put<'a>(&'a self, key: &'a str, bytes: Vec<u8>) -> BoxFuture<'a, Result<(), BlobError>>;
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, BlobError>>;
+
+    /// Deleting a missing key is not an error.
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), BlobError>>;
+}
+
+pub fn blob_store_from_configuration(
+    blob_store_config: &BlobStoreConfiguration,
+    client: &Database,
+) -> Arc<dyn BlobStore> {
+    match blob_store_config {
+        BlobStoreConfiguration::Filesystem { path } => {
+            Arc::new(FilesystemBlobStore::new(path.clone()))
+        }
+        BlobStoreConfiguration::Gridfs { bucket } => {
+            Arc::new(GridFsBlobStore::new(client, bucket.clone()))
+        }
+    }
+}
+
+/// Keeps every blob in a file under `root`, named after its key.
+pub struct FilesystemBlobStore {
+    root: PathBuf,
+}
+
+impl FilesystemBlobStore {
+    pub fn new(root: PathBuf) -> Self {
+        FilesystemBlobStore { root }
+    }
+}
+
+impl BlobStore for FilesystemBlobStore {
+    fn put<'a>(&'a self, key: &'a str, bytes: Vec<u8>) -> BoxFuture<'a, Result<(), BlobError>> {
+        Box::pin(async move {
+            let path = self.root.join(key);
+            if let Some(directory) = path.parent() {
+                tokio::fs::create_dir_all(directory).await?;
+            }
+            // Renaming is atomic, so readers never see a half-written blob.
+            let partial_path = path.with_extension("partial");
+            tokio::fs::write(&partial_path, bytes).await?;
+            tokio::fs::rename(&partial_path, &path).await?;
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, BlobError>> {
+        Box::pin(async move {
+            match tokio::fs::read(self.root.join(key)).await {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(error) => Err(error.into()),
+            }
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), BlobError>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.root.join(key)).await {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+/// Keeps every blob in a MongoDB GridFS bucket, as a file named after its key.
+pub struct GridFsBlobStore {
+    bucket: GridFsBucket,
+}
+
+impl GridFsBlobStore {
+    pub fn new(client: &Database, bucket: String) -> Self {
+        GridFsBlobStore {
+            bucket: client
+                .gridfs_bucket(GridFsBucketOptions::builder().bucket_name(bucket).build()),
+        }
+    }
+
+    /// Deletes the files named `key`, except the one with `kept_id`.
+    async fn delete_except(
+        &self,
+        key: &str,
+        kept_id: Option<mongodb::bson::Bson>,
+    ) -> Result<(), BlobError> {
+        let files: Vec<_> = self
+            .bucket
+            .find(doc! {"filename": key}, None)
+            .await?
+            .try_collect()
+            .await?;
+        for file in files {
+            if Some(&file.id) != kept_id.as_ref() {
+                self.bucket.delete(file.id).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BlobStore for GridFsBlobStore {
+    fn put<'a>(&'a self, key: &'a str, bytes: Vec<u8>) -> BoxFuture<'a, Result<(), BlobError>> {
+        Box::pin(async move {
+            let id = self
+                .bucket
+                .upload_from_futures_0_3_reader(key, futures::io::Cursor::new(bytes), None)
+                .await?;
+            // GridFS keeps every upload of a name as a revision; only the latest one is needed.
+            self.delete_except(key, Some(id.into())).await
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<Option<Vec<u8>>, BlobError>> {
+        Box::pin(async move {
+            // Looked up first, since a missing file can't be told apart from other download
+            // errors.
+            let options = GridFsFindOptions::builder()
+                .sort(doc! {"uploadDate": -1})
+                .limit(1)
+                .build();
+            let Some(file) = self
+                .bucket
+                .find(doc! {"filename": key}, options)
+                .await?
+                .try_next()
+                .await?
+            else {
+                return Ok(None);
+            };
+            let mut bytes = Vec::new();
+            self.bucket
+                .download_to_futures_0_3_writer(file.id, &mut bytes)
+                .await?;
+            Ok(Some(bytes))
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), BlobError>> {
+        Box::pin(self.delete_except(key, None))
+    }
+}
@@ -1,19 +1,212 @@
-#[derive(serde::Deserialize)]
+use std::collections::HashSet;
+
+#[derive(serde::Deserialize, serde::Serialize)]
 pub struct StaticConfiguration {
     pub database: DatabaseConfiguration,
     pub application_port: u16,
+    #[serde(default)]
+    pub stack_aliases: Vec<StackAliasConfiguration>,
+    /// Enables the embedded full-text index when set.
+    pub search_index: Option<SearchIndexConfiguration>,
+    pub live_search: LiveSearchConfiguration,
+    pub webhooks: WebhookConfiguration,
+    pub outbox: OutboxConfiguration,
+    pub idempotency: IdempotencyConfiguration,
+    pub soft_delete: SoftDeleteConfiguration,
+    pub avatars: AvatarConfiguration,
+    pub birth_date: BirthDateConfiguration,
+    /// Serves several tenants, each from its own database, when set. `database` is then only the
+    /// server and credentials they are all stored with.
+    pub tenancy: Option<TenancyConfiguration>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct TenancyConfiguration {
+    pub resolve_from: TenantResolution,
+    pub tenants: Vec<TenantConfiguration>,
+}
+
+impl TenancyConfiguration {
+    /// Every tenant needs its own id and database: idempotency keys, webhooks, migrations and
+    /// GridFS avatars are kept per database, so tenants sharing one would see each other's.
+    /// Tenant ids are matched ignoring case, so they are lowercased here.
+    pub fn validate(&mut self) -> Result<(), String> {
+        for tenant in &mut self.tenants {
+            tenant.id = tenant.id.to_lowercase();
+        }
+        let mut ids = HashSet::new();
+        let mut database_names = HashSet::new();
+        for tenant in &self.tenants {
+            if !ids.insert(tenant.id.as_str()) {
+                return Err(format!("tenant {} is configured more than once", tenant.id));
+            }
+            if !database_names.insert(tenant.database_name.as_str()) {
+                return Err(format!(
+                    "tenant {} shares the database {} with another tenant",
+                    tenant.id, tenant.database_name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What part of a request names its tenant.
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TenantResolution {
+    /// The value of a header.
+    Header { name: String },
+    /// The leftmost label of the `Host`, as in `acme.api.example.com`.
+    Subdomain,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct TenantConfiguration {
+    pub id: String,
+    pub database_name: String,
+    /// Defaults to the `database` one.
+    pub collection_name: Option<String>,
+}
+
+impl StaticConfiguration {
+    /// The database of `tenant_id`, which must be given exactly when tenants are configured.
+    pub fn database_for(&self, tenant_id: Option<&str>) -> Result<DatabaseConfiguration, String> {
+        match (&self.tenancy, tenant_id) {
+            (None, None) => Ok(self.database.clone()),
+            (None, Some(_)) => Err(String::from("no tenants are configured")),
+            (Some(_), None) => Err(String::from("a tenant is required")),
+            (Some(tenancy), Some(tenant_id)) => tenancy
+                .tenants
+                .iter()
+                .find(|tenant| tenant.id.to_lowercase() == tenant_id.to_lowercase())
+                .map(|tenant| self.database.for_tenant(tenant))
+                .ok_or_else(|| format!("unknown tenant {}", tenant_id)),
+        }
+    }
+}
+
+/// Birth dates in the future are always rejected; these bound the age of new persons.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct BirthDateConfiguration {
+    pub min_age_years: u32,
+    pub max_age_years: u32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct AvatarConfiguration {
+    /// Larger uploads get a 413.
+    pub max_bytes: usize,
+    /// Images wider or taller than this are rejected before being decoded.
+    pub max_dimension: u32,
+    /// Bounding boxes, in pixels, of the thumbnails made of every avatar.
+    pub thumbnail_sizes: Vec<u32>,
+    /// How long clients may cache an avatar without revalidating it.
+    pub cache_max_age_seconds: u64,
+    pub store: BlobStoreConfiguration,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BlobStoreConfiguration {
+    /// Files under a local directory.
+    Filesystem { path: std::path::PathBuf },
+    /// A GridFS bucket of the application database.
+    Gridfs { bucket: String },
 }
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SoftDeleteConfiguration {
+    /// How long deleted persons can be restored before they are purged.
+    pub retention_seconds: u64,
+    pub purge_interval_ms: u64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct IdempotencyConfiguration {
+    /// How long the response to a request with an `Idempotency-Key` is replayed.
+    pub ttl_seconds: u64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct OutboxConfiguration {
+    /// How often the relay looks for unpublished events, and waits before retrying a failed one.
+    pub poll_interval_ms: u64,
+    pub sink: OutboxSinkConfiguration,
+}
+
+/// Where the outbox relay publishes person events.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OutboxSinkConfiguration {
+    /// Logs every event.
+    Log,
+    /// POSTs every event as JSON.
+    Http { url: String },
+    /// Publishes every event to a subject of a NATS server, given as `host:port`.
+    Nats { address: String, subject: String },
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct WebhookConfiguration {
+    /// Attempts before a delivery is moved to the dead letters.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled after every further failure.
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// How often the worker looks for due retries when nothing new was enqueued.
+    pub poll_interval_ms: u64,
+    pub request_timeout_ms: u64,
+    /// Allows webhooks to this machine and private networks, which are refused by default so
+    /// that webhooks cannot reach internal services.
+    pub allow_private_hosts: bool,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct LiveSearchConfiguration {
+    /// Open WebSocket connections allowed at once; further upgrades get a 503.
+    pub max_connections: usize,
+    /// Terms a single connection may be subscribed to at once.
+    pub max_subscriptions: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct SearchIndexConfiguration {
+    pub path: std::path::PathBuf,
+    /// How long changes may wait to be committed together before they become searchable.
+    pub commit_interval_ms: u64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct StackAliasConfiguration {
+    pub canonical: String,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
 pub struct DatabaseConfiguration {
     pub username: String,
+    #[serde(serialize_with = "redact")]
     pub password: String,
     pub port: u16,
     pub host: String,
     pub database_name: String,
+    /// Where persons are stored.
+    pub collection_name: String,
 }
 
 impl DatabaseConfiguration {
+    pub fn for_tenant(&self, tenant: &TenantConfiguration) -> Self {
+        DatabaseConfiguration {
+            database_name: tenant.database_name.clone(),
+            collection_name: tenant
+                .collection_name
+                .clone()
+                .unwrap_or_else(|| self.collection_name.clone()),
+            ..self.clone()
+        }
+    }
+
     pub fn connection_string(&self) -> String {
         format!(
             "mongodb://{}:{}@{}:{}",
@@ -22,6 +215,11 @@ impl DatabaseConfiguration {
     }
 }
 
+/// Keeps secrets out of printed configurations.
+fn redact<S: serde::Serializer>(_secret: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str("[REDACTED]")
+}
+
 pub fn get_static_configuration() -> Result<StaticConfiguration, config::ConfigError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
@@ -30,7 +228,7 @@ pub fn get_static_configuration() -> Result<StaticConfiguration, config::ConfigE
         .try_into()
         .expect("Failed to parse APP_ENVIRONMENT");
 
-    config::Config::builder()
+    let mut static_config: StaticConfiguration = config::Config::builder()
         .add_source(config::File::from(configuration_directory.join("base")).required(true))
         .add_source(
             config::File::from(configuration_directory.join(environment.as_str())).required(true),
@@ -41,7 +239,11 @@ pub fn get_static_configuration() -> Result<StaticConfiguration, config::ConfigE
                 .separator("__"),
         )
         .build()?
-        .try_deserialize()
+        .try_deserialize()?;
+    if let Some(tenancy) = &mut static_config.tenancy {
+        tenancy.validate().map_err(config::ConfigError::Message)?;
+    }
+    Ok(static_config)
 }
 
 enum Environment {
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use tokio::sync::{broadcast, Notify};
+
+use crate::search_index::{self, SearchIndex};
+use crate::structs::person;
+
+pub const PERSON_CREATED_EVENT: &str = "pessoa.criada";
+
+/// How many created persons a slow feed subscriber may fall behind before skipping ahead.
+pub const PERSON_FEED_CAPACITY: usize = 1024;
+
+/// Everything that must hear about persons once they are stored.
+#[derive(Clone, Default)]
+pub struct PersonListeners {
+    pub search_index: Option<Arc<SearchIndex>>,
+    pub feed: Option<broadcast::Sender<person::Person>>,
+    /// Wakes the outbox relay, which otherwise only polls for new events. Webhooks are queued by
+    /// the relay.
+    pub outbox: Option<Arc<Notify>>,
+}
+
+impl PersonListeners {
+    pub async fn created(&self, devs: Vec<person::Person>) {
+        if let Some(feed) = &self.feed {
+            for dev in &devs {
+                // Only fails when nobody is subscribed, which is fine.
+                let _ = feed.send(dev.clone());
+            }
+        }
+        if let Some(outbox) = &self.outbox {
+            outbox.notify_one();
+        }
+        search_index::index_persons(self.search_index.clone(), devs).await;
+    }
+}
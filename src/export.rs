@@ -0,0 +1,192 @@
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::NaiveDate;
+use futures::stream::{Stream, TryStreamExt};
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType, Int32Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::structs::{api, person};
+
+const CSV_HEADER: [&str; 5] = ["id", "apelido", "nome", "nascimento", "stack"];
+const CSV_STACK_SEPARATOR: &str = ";";
+const PARQUET_ROW_GROUP_SIZE: usize = 10_000;
+const PARQUET_SCHEMA: &str = "
+    message pessoa {
+        required binary id (STRING);
+        required binary apelido (STRING);
+        required binary nome (STRING);
+        required int32 nascimento (DATE);
+        repeated binary stack (STRING);
+    }
+";
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    Csv,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    /// Bytes written once before the first person, if the format has any.
+    pub fn header(&self) -> Option<Vec<u8>> {
+        match self {
+            ExportFormat::Csv => Some(csv_line(&CSV_HEADER)),
+            _ => None,
+        }
+    }
+
+    /// Encodes a single person as a line-delimited record. Parquet is columnar and cannot be
+    /// encoded row by row, see [`write_parquet`].
+    pub fn encode(&self, dev: person::Person) -> std::io::Result<Vec<u8>> {
+        let body = api::PersonBody::from(dev);
+        match self {
+            ExportFormat::Ndjson => {
+                let mut line = serde_json::to_vec(&body)?;
+                line.push(b'\n');
+                Ok(line)
+            }
+            ExportFormat::Csv => Ok(csv_line(&[
+                body.id.to_string(),
+                body.nickname,
+                body.name,
+                body.birth_date.to_string(),
+                body.stacks.unwrap_or_default().join(CSV_STACK_SEPARATOR),
+            ])),
+            ExportFormat::Parquet => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "parquet can't be encoded row by row",
+            )),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            "csv" => Ok(ExportFormat::Csv),
+            "parquet" => Ok(ExportFormat::Parquet),
+            other => Err(format!("{} is not a valid export format", other)),
+        }
+    }
+}
+
+fn csv_line<T: AsRef<[u8]>>(record: &[T]) -> Vec<u8> {
+    let mut writer = csv::WriterBuilder::new().from_writer(vec![]);
+    writer
+        .write_record(record)
+        .expect("writing csv to memory can't fail");
+    writer
+        .into_inner()
+        .expect("flushing csv to memory can't fail")
+}
+
+/// Writes every person yielded by `devs` into a Parquet file, in row groups of
+/// `PARQUET_ROW_GROUP_SIZE` so the whole collection never sits in memory.
+pub async fn write_parquet<S, W>(
+    devs: S,
+    sink: W,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<person::Person, mongodb::error::Error>>,
+    W: Write + Send,
+{
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+    let properties = Arc::new(
+        WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build(),
+    );
+    let mut writer = SerializedFileWriter::new(sink, schema, properties)?;
+
+    let mut chunks = std::pin::pin!(devs.try_chunks(PARQUET_ROW_GROUP_SIZE));
+    while let Some(chunk) = chunks.try_next().await.map_err(|error| error.1)? {
+        let mut row_group = writer.next_row_group()?;
+        let mut column_index = 0;
+        while let Some(mut column) = row_group.next_column()? {
+            match column_index {
+                0 => write_strings(&mut column, chunk.iter().map(|dev| dev.id.to_string()))?,
+                1 => write_strings(&mut column, chunk.iter().map(|dev| dev.nickname.clone()))?,
+                2 => write_strings(&mut column, chunk.iter().map(|dev| dev.name.clone()))?,
+                3 => {
+                    let days: Vec<i32> = chunk
+                        .iter()
+                        .map(|dev| days_since_epoch(dev.birth_date))
+                        .collect();
+                    column.typed::<Int32Type>().write_batch(&days, None, None)?
+                }
+                _ => write_stacks(&mut column, &chunk)?,
+            };
+            column.close()?;
+            column_index += 1;
+        }
+        row_group.close()?;
+    }
+    writer.close()?;
+    Ok(())
+}
+
+fn write_strings(
+    column: &mut parquet::file::writer::SerializedColumnWriter<'_>,
+    values: impl Iterator<Item = String>,
+) -> parquet::errors::Result<usize> {
+    let values: Vec<ByteArray> = values
+        .map(|value| ByteArray::from(value.into_bytes()))
+        .collect();
+    column
+        .typed::<ByteArrayType>()
+        .write_batch(&values, None, None)
+}
+
+fn write_stacks(
+    column: &mut parquet::file::writer::SerializedColumnWriter<'_>,
+    devs: &[person::Person],
+) -> parquet::errors::Result<usize> {
+    let mut values = vec![];
+    let mut definition_levels = vec![];
+    let mut repetition_levels = vec![];
+    for dev in devs {
+        match dev.display_stacks().map(Vec::as_slice) {
+            Some(stacks) if !stacks.is_empty() => {
+                for (index, stack) in stacks.iter().enumerate() {
+                    values.push(ByteArray::from(stack.as_str()));
+                    definition_levels.push(1);
+                    repetition_levels.push(if index == 0 { 0 } else { 1 });
+                }
+            }
+            _ => {
+                definition_levels.push(0);
+                repetition_levels.push(0);
+            }
+        }
+    }
+    column.typed::<ByteArrayType>().write_batch(
+        &values,
+        Some(&definition_levels),
+        Some(&repetition_levels),
+    )
+}
+
+fn days_since_epoch(date: NaiveDate) -> i32 {
+    (date - NaiveDate::default()).num_days() as i32
+}
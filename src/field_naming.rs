@@ -0,0 +1,133 @@
+use std::convert::Infallible;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, HeaderMap};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::structs::api;
+
+/// Asks for person bodies with English field names when accepted by a request.
+pub const ENGLISH_MEDIA_TYPE: &str = "application/vnd.rinha.en+json";
+
+/// The field names persons are rendered with. Requests get the contest's Portuguese names unless
+/// they accept [`ENGLISH_MEDIA_TYPE`]; either naming is accepted on input.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FieldNaming {
+    #[default]
+    Portuguese,
+    English,
+}
+
+impl FieldNaming {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let wants_english = headers
+            .get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains(ENGLISH_MEDIA_TYPE));
+        if wants_english {
+            FieldNaming::English
+        } else {
+            FieldNaming::Portuguese
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FieldNaming::Portuguese => "application/json",
+            FieldNaming::English => ENGLISH_MEDIA_TYPE,
+        }
+    }
+
+    pub fn render<T: Localized>(&self, body: T) -> Response {
+        let content_type = [(header::CONTENT_TYPE, self.content_type())];
+        match self {
+            FieldNaming::Portuguese => (content_type, Json(body)).into_response(),
+            FieldNaming::English => (content_type, Json(body.into_english())).into_response(),
+        }
+    }
+
+    /// Renders a body stored as Portuguese JSON.
+    pub fn render_stored<T: Localized + DeserializeOwned>(
+        &self,
+        body: String,
+    ) -> serde_json::Result<String> {
+        match self {
+            FieldNaming::Portuguese => Ok(body),
+            FieldNaming::English => {
+                serde_json::to_string(&serde_json::from_str::<T>(&body)?.into_english())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for FieldNaming {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(FieldNaming::from_headers(&parts.headers))
+    }
+}
+
+/// A body that also has a rendering with English field names.
+pub trait Localized: Serialize {
+    type English: Serialize;
+
+    fn into_english(self) -> Self::English;
+}
+
+impl Localized for api::PersonBody {
+    type English = api::EnglishPersonBody;
+
+    fn into_english(self) -> Self::English {
+        api::EnglishPersonBody {
+            id: self.id,
+            nickname: self.nickname,
+            name: self.name,
+            birth_date: self.birth_date,
+            stacks: self.stacks,
+            stack_details: self.stack_details,
+            age: self.age,
+            email: self.email,
+            location: self.location,
+            seniority: self.seniority,
+            github: self.github,
+            bio: self.bio,
+        }
+    }
+}
+
+impl Localized for api::ScoredPersonBody {
+    type English = api::EnglishScoredPersonBody;
+
+    fn into_english(self) -> Self::English {
+        api::EnglishScoredPersonBody {
+            person: self.person.into_english(),
+            score: self.score,
+        }
+    }
+}
+
+impl<T: Localized> Localized for api::Envelope<T> {
+    type English = api::Envelope<T::English>;
+
+    fn into_english(self) -> Self::English {
+        api::Envelope {
+            data: self.data.into_english(),
+            meta: self.meta,
+        }
+    }
+}
+
+impl<T: Localized> Localized for Vec<T> {
+    type English = Vec<T::English>;
+
+    fn into_english(self) -> Self::English {
+        self.into_iter().map(Localized::into_english).collect()
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use axum::async_trait;
+use axum::body::{Body, Bytes};
+use axum::extract::rejection::{BytesRejection, JsonRejection};
+use axum::extract::{FromRequest, Request};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::IndexOptions;
+use mongodb::{Collection, Database, IndexModel};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+
+use crate::configuration::IdempotencyConfiguration;
+use crate::storage::is_duplicate_key;
+use crate::structs::idempotency::{IdempotencyRecord, StoredResponse};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Set on responses replayed from an earlier request with the same key.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_KEY_LENGTH: usize = 255;
+/// A request still unanswered after this long is assumed to have died with its server, and its key
+/// may be reused.
+const IN_FLIGHT_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidIdempotencyKey;
+
+/// The `Idempotency-Key` of a request, if it has a valid one: 1 to 255 visible ASCII characters.
+pub fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, InvalidIdempotencyKey> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key)
+            if !key.is_empty()
+                && key.len() <= MAX_KEY_LENGTH
+                && key.chars().all(|character| character.is_ascii_graphic()) =>
+        {
+            Ok(Some(key.to_string()))
+        }
+        _ => Err(InvalidIdempotencyKey),
+    }
+}
+
+/// A JSON body along with the SHA-256 of its raw bytes, telling retries of the same request apart
+/// from different requests reusing a key. Rejects requests exactly like [`Json`].
+pub struct HashedJson<T> {
+    pub value: T,
+    pub hash: String,
+}
+
+#[async_trait]
+impl<T, S> FromRequest<S> for HashedJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = HashedJsonRejection;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+        let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), state)
+            .await
+            .map_err(HashedJsonRejection::Bytes)?;
+        let hash = hex::encode(Sha256::digest(&bytes));
+        let Json(value) =
+            Json::<T>::from_request(Request::from_parts(parts, Body::from(bytes)), state)
+                .await
+                .map_err(HashedJsonRejection::Json)?;
+        Ok(HashedJson { value, hash })
+    }
+}
+
+#[derive(Debug)]
+pub enum HashedJsonRejection {
+    Bytes(BytesRejection),
+    Json(JsonRejection),
+}
+
+impl HashedJsonRejection {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            HashedJsonRejection::Bytes(rejection) => rejection.status(),
+            HashedJsonRejection::Json(rejection) => rejection.status(),
+        }
+    }
+
+    pub fn body_text(&self) -> String {
+        match self {
+            HashedJsonRejection::Bytes(rejection) => rejection.body_text(),
+            HashedJsonRejection::Json(rejection) => rejection.body_text(),
+        }
+    }
+}
+
+impl IntoResponse for HashedJsonRejection {
+    fn into_response(self) -> Response {
+        match self {
+            HashedJsonRejection::Bytes(rejection) => rejection.into_response(),
+            HashedJsonRejection::Json(rejection) => rejection.into_response(),
+        }
+    }
+}
+
+pub enum Reservation {
+    /// The key is new: handle the request, then [`IdempotencyStore::complete`] or
+    /// [`IdempotencyStore::release`] it.
+    Reserved,
+    /// The request was already handled; replay its response.
+    Completed(StoredResponse),
+    /// The same request is being handled right now.
+    InFlight,
+    /// The key was used for a request with a different body.
+    Mismatch,
+}
+
+/// Remembers the responses to requests made with an `Idempotency-Key` for a while, so that retries
+/// get the original response instead of creating another person.
+pub struct IdempotencyStore {
+    client: Database,
+    ttl: Duration,
+}
+
+impl IdempotencyStore {
+    pub fn new(client: Database, idempotency_config: &IdempotencyConfiguration) -> Self {
+        IdempotencyStore {
+            client,
+            ttl: Duration::from_secs(idempotency_config.ttl_seconds),
+        }
+    }
+
+    fn keys_store(&self) -> Collection<IdempotencyRecord> {
+        self.client.collection("idempotency_keys")
+    }
+
+    /// Lets MongoDB drop keys once they expire.
+    pub async fn ensure_index(&self) -> Result<(), mongodb::error::Error> {
+        let expiry_index = IndexModel::builder()
+            .keys(doc! {"expires_at": 1})
+            .options(IndexOptions::builder().expire_after(Duration::ZERO).build())
+            .build();
+        self.keys_store().create_index(expiry_index, None).await?;
+        Ok(())
+    }
+
+    pub async fn reserve(
+        &self,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<Reservation, mongodb::error::Error> {
+        loop {
+            let now = DateTime::now();
+            let record = IdempotencyRecord {
+                key: key.to_string(),
+                request_hash: request_hash.to_string(),
+                response: None,
+                created_at: now,
+                expires_at: DateTime::from_millis(
+                    now.timestamp_millis() + self.ttl.as_millis() as i64,
+                ),
+            };
+            match self.keys_store().insert_one(&record, None).await {
+                Ok(_) => return Ok(Reservation::Reserved),
+                Err(error) if !is_duplicate_key(&error) => return Err(error),
+                Err(_) => {}
+            }
+
+            let Some(existing) = self.keys_store().find_one(doc! {"_id": key}, None).await? else {
+                // Released or expired in the meantime.
+                continue;
+            };
+            let abandoned = existing.response.is_none()
+                && existing.created_at.timestamp_millis() + (IN_FLIGHT_TIMEOUT.as_millis() as i64)
+                    < now.timestamp_millis();
+            // The TTL monitor only runs every minute, so expired keys may still be around.
+            if existing.expires_at < now || abandoned {
+                self.keys_store()
+                    .delete_one(doc! {"_id": key, "created_at": existing.created_at}, None)
+                    .await?;
+                continue;
+            }
+            if existing.request_hash != request_hash {
+                return Ok(Reservation::Mismatch);
+            }
+            return Ok(match existing.response {
+                Some(response) => Reservation::Completed(response),
+                None => Reservation::InFlight,
+            });
+        }
+    }
+
+    /// Stores the response to replay for `key`.
+    pub async fn complete(
+        &self,
+        key: &str,
+        response: StoredResponse,
+    ) -> Result<(), mongodb::error::Error> {
+        let response = mongodb::bson::to_bson(&response)?;
+        self.keys_store()
+            .update_one(
+                doc! {"_id": key},
+                doc! {"$set": {"response": response}},
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Forgets `key` after its request failed, so that it can be retried.
+    pub async fn release(&self, key: &str) -> Result<(), mongodb::error::Error> {
+        self.keys_store()
+            .delete_one(doc! {"_id": key, "response": null}, None)
+            .await?;
+        Ok(())
+    }
+}
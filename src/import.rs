@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use mongodb::error::ErrorKind;
+use mongodb::options::InsertManyOptions;
+use mongodb::Collection;
+use serde::Serialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use uuid::Uuid;
+
+use crate::events::PersonListeners;
+use crate::normalization::StackAliases;
+use crate::storage::{PersonStore, DUPLICATE_KEY_CODE};
+use crate::structs::{api, person};
+use crate::validation::{self, BirthDateRules};
+
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+/// Longer lines are reported as failed without being buffered.
+pub const MAX_LINE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub inserted: usize,
+    pub failed: usize,
+    pub lines: Vec<LineResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineResult {
+    pub line: usize,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ImportReport {
+    fn push_success(&mut self, line: usize, id: Uuid) {
+        self.inserted += 1;
+        self.lines.push(LineResult {
+            line,
+            status: 201,
+            id: Some(id),
+            error: None,
+        });
+    }
+
+    fn push_failure(&mut self, line: usize, status: u16, error: String) {
+        self.failed += 1;
+        self.lines.push(LineResult {
+            line,
+            status,
+            id: None,
+            error: Some(error),
+        });
+    }
+}
+
+/// Reads NDJSON persons from `reader`, one `CreatePersonBody` per line, inserting them in batches
+/// of `batch_size`. Blank lines are skipped; every other line gets an entry in the report, lines
+/// longer than `MAX_LINE_BYTES` failing with a 413.
+pub async fn import_persons<R>(
+    client: &PersonStore,
+    stack_aliases: &StackAliases,
+    birth_date_rules: &BirthDateRules,
+    listeners: &PersonListeners,
+    mut reader: R,
+    batch_size: usize,
+) -> Result<ImportReport, std::io::Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let devs_store = client.persons();
+    let mut report = ImportReport::default();
+    let mut batch: Vec<(usize, person::Person)> = Vec::with_capacity(batch_size);
+    let mut line = Vec::new();
+    let mut line_number = 0;
+
+    while let Some(fits) = read_line(&mut reader, &mut line, MAX_LINE_BYTES).await? {
+        line_number += 1;
+        if !fits {
+            report.push_failure(
+                line_number,
+                413,
+                format!("line exceeds {} bytes", MAX_LINE_BYTES),
+            );
+            continue;
+        }
+        let Ok(line) = std::str::from_utf8(&line) else {
+            report.push_failure(line_number, 422, String::from("line is not valid UTF-8"));
+            continue;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<api::CreatePersonBody>(line) {
+            Ok(body) => match validation::validate_person(&body, birth_date_rules) {
+                Ok(()) => batch.push((line_number, person::Person::new(body, stack_aliases))),
+                Err(invalid) => report.push_failure(line_number, 422, invalid.to_string()),
+            },
+            Err(error) => report.push_failure(line_number, 422, error.to_string()),
+        }
+        if batch.len() >= batch_size {
+            let inserted = insert_batch(&devs_store, std::mem::take(&mut batch), &mut report).await;
+            listeners.created(inserted).await;
+        }
+    }
+    if !batch.is_empty() {
+        let inserted = insert_batch(&devs_store, batch, &mut report).await;
+        listeners.created(inserted).await;
+    }
+
+    report.lines.sort_by_key(|result| result.line);
+    Ok(report)
+}
+
+/// Reads the next line into `line`, without its line ending, buffering at most `max_bytes`.
+/// Returns `None` at the end of the input, otherwise whether the line fit; longer lines are
+/// skipped up to their newline and leave `line` empty.
+async fn read_line<R>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    max_bytes: usize,
+) -> Result<Option<bool>, std::io::Error>
+where
+    R: AsyncBufRead + Unpin,
+{
+    line.clear();
+    let mut read_any = false;
+    let mut fits = true;
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(read_any.then_some(fits));
+        }
+        read_any = true;
+        let newline = available.iter().position(|byte| *byte == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        if fits && line.len() + chunk.len() <= max_bytes {
+            line.extend_from_slice(chunk);
+        } else {
+            fits = false;
+            line.clear();
+        }
+        let consumed = newline.map_or(available.len(), |newline| newline + 1);
+        reader.consume(consumed);
+        if newline.is_some() {
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            return Ok(Some(fits));
+        }
+    }
+}
+
+/// Inserts `batch`, recording each line's outcome, and returns the persons actually stored.
+async fn insert_batch(
+    devs_store: &Collection<person::Person>,
+    batch: Vec<(usize, person::Person)>,
+    report: &mut ImportReport,
+) -> Vec<person::Person> {
+    let options = InsertManyOptions::builder().ordered(false).build();
+    let inserted_result = devs_store
+        .insert_many(batch.iter().map(|(_, dev)| dev), options)
+        .await;
+
+    let failed_indexes: HashMap<usize, (u16, String)> = match inserted_result {
+        Ok(_) => HashMap::new(),
+        Err(error) => match *error.kind {
+            ErrorKind::BulkWrite(failure) => failure
+                .write_errors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|write_error| {
+                    let status = if write_error.code == DUPLICATE_KEY_CODE {
+                        422
+                    } else {
+                        500
+                    };
+                    (write_error.index, (status, write_error.message))
+                })
+                .collect(),
+            _ => {
+                println!("bulk: {}", error);
+                (0..batch.len())
+                    .map(|index| (index, (500, error.to_string())))
+                    .collect()
+            }
+        },
+    };
+
+    let mut inserted = Vec::with_capacity(batch.len());
+    for (index, (line, dev)) in batch.into_iter().enumerate() {
+        match failed_indexes.get(&index) {
+            Some((status, message)) => report.push_failure(line, *status, message.clone()),
+            None => {
+                report.push_success(line, dev.id);
+                inserted.push(dev);
+            }
+        }
+    }
+    inserted
+}
@@ -1,5 +1,26 @@
+pub mod admin;
+pub mod age;
+pub mod avatars;
+pub mod blob_store;
 pub mod configuration;
+pub mod events;
+pub mod export;
+pub mod field_naming;
+pub mod idempotency;
+pub mod import;
+pub mod live_search;
+pub mod migrations;
+pub mod normalization;
+pub mod openapi;
+pub mod outbox;
+pub mod purge;
 pub mod routes;
+pub mod search;
+pub mod search_index;
 pub mod startup;
+pub mod storage;
 pub mod structs;
 pub mod telemetry;
+pub mod tenancy;
+pub mod validation;
+pub mod webhooks;
@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+use crate::configuration::LiveSearchConfiguration;
+use crate::normalization::StackAliases;
+use crate::search;
+use crate::search_index::SearchIndex;
+use crate::storage::PersonStore;
+use crate::structs::{api, person};
+
+/// Caps on the live search WebSocket connections, shared by all of them.
+pub struct LiveSearchLimits {
+    connections: Arc<Semaphore>,
+    max_subscriptions: usize,
+}
+
+impl LiveSearchLimits {
+    pub fn new(live_search_config: &LiveSearchConfiguration) -> Self {
+        LiveSearchLimits {
+            connections: Arc::new(Semaphore::new(live_search_config.max_connections)),
+            max_subscriptions: live_search_config.max_subscriptions,
+        }
+    }
+
+    /// Reserves a connection slot, released when the permit is dropped, or `None` when all of
+    /// them are taken.
+    pub fn try_connect(&self) -> Option<OwnedSemaphorePermit> {
+        self.connections.clone().try_acquire_owned().ok()
+    }
+}
+
+/// A live search client connection and what it needs to search the terms it subscribes to.
+pub struct LiveSearch {
+    client: PersonStore,
+    stack_aliases: Arc<StackAliases>,
+    search_index: Option<Arc<SearchIndex>>,
+    feed: broadcast::Receiver<person::Person>,
+    max_subscriptions: usize,
+}
+
+impl LiveSearch {
+    pub fn new(
+        client: PersonStore,
+        stack_aliases: Arc<StackAliases>,
+        search_index: Option<Arc<SearchIndex>>,
+        feed: &broadcast::Sender<person::Person>,
+        limits: &LiveSearchLimits,
+    ) -> Self {
+        LiveSearch {
+            client,
+            stack_aliases,
+            search_index,
+            // Subscribed up front so persons created while the initial results are being
+            // searched are not missed.
+            feed: feed.subscribe(),
+            max_subscriptions: limits.max_subscriptions,
+        }
+    }
+
+    /// Serves the connection until the client leaves. Every message is awaited until the socket
+    /// accepts it, so a slow client only holds back its own feed receiver, which skips ahead with a
+    /// `lagged` event once it falls `PERSON_FEED_CAPACITY` persons behind.
+    pub async fn serve(mut self, mut socket: WebSocket) {
+        // The ids sent as each term's initial results, so they are not sent again as matches.
+        let mut subscriptions: BTreeMap<String, HashSet<Uuid>> = BTreeMap::new();
+
+        loop {
+            let events = tokio::select! {
+                message = socket.recv() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        self.handle_request(&text, &mut subscriptions).await
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => vec![],
+                },
+                created = self.feed.recv() => match created {
+                    Ok(dev) => self.matches(dev, &subscriptions),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        println!("live search: skipped {} persons", skipped);
+                        if subscriptions.is_empty() {
+                            vec![]
+                        } else {
+                            vec![api::LiveSearchEvent::Lagged { skipped }]
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+
+            for event in events {
+                let text = match serde_json::to_string(&event) {
+                    Ok(text) => text,
+                    Err(error) => {
+                        println!("live search: {}", error);
+                        continue;
+                    }
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn handle_request(
+        &self,
+        text: &str,
+        subscriptions: &mut BTreeMap<String, HashSet<Uuid>>,
+    ) -> Vec<api::LiveSearchEvent> {
+        let request = match serde_json::from_str::<api::LiveSearchRequest>(text) {
+            Ok(request) => request,
+            Err(error) => return vec![error_event(error)],
+        };
+        match request {
+            api::LiveSearchRequest::Subscribe { term } => {
+                if term.trim().is_empty() {
+                    return vec![error_event("term must not be empty")];
+                }
+                if !subscriptions.contains_key(&term)
+                    && subscriptions.len() >= self.max_subscriptions
+                {
+                    return vec![error_event(format!(
+                        "at most {} terms can be subscribed to at once",
+                        self.max_subscriptions
+                    ))];
+                }
+
+                let query = api::SearchPersonQuery {
+                    search_term: Some(term.clone()),
+                    ..Default::default()
+                };
+                match search::find_persons(
+                    &self.client,
+                    &self.stack_aliases,
+                    self.search_index.clone(),
+                    &query,
+                )
+                .await
+                {
+                    Ok(found_devs) => {
+                        let sent_ids = found_devs.iter().map(|(dev, _)| dev.id).collect();
+                        subscriptions.insert(term.clone(), sent_ids);
+                        vec![api::LiveSearchEvent::Results {
+                            term,
+                            persons: found_devs
+                                .into_iter()
+                                .map(|(dev, _)| api::PersonBody::from(dev))
+                                .collect(),
+                        }]
+                    }
+                    Err(error) => {
+                        println!("live search: {}", error);
+                        vec![error_event(error)]
+                    }
+                }
+            }
+            api::LiveSearchRequest::Unsubscribe { term } => {
+                subscriptions.remove(&term);
+                vec![]
+            }
+        }
+    }
+
+    fn matches(
+        &self,
+        dev: person::Person,
+        subscriptions: &BTreeMap<String, HashSet<Uuid>>,
+    ) -> Vec<api::LiveSearchEvent> {
+        subscriptions
+            .iter()
+            .filter(|(term, sent_ids)| {
+                !sent_ids.contains(&dev.id) && search::matches(&dev, term, &self.stack_aliases)
+            })
+            .map(|(term, _)| api::LiveSearchEvent::Match {
+                term: term.clone(),
+                person: api::PersonBody::from(dev.clone()),
+            })
+            .collect()
+    }
+}
+
+fn error_event(error: impl std::fmt::Display) -> api::LiveSearchEvent {
+    api::LiveSearchEvent::Error {
+        message: error.to_string(),
+    }
+}
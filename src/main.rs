@@ -1,18 +1,261 @@
-use std::io::Error;
+use std::io::{Error, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand};
+use futures::stream::TryStreamExt;
+use tantivy::directory::error::LockError;
+use tantivy::TantivyError;
 use tracing_subscriber::EnvFilter;
 
-use rinha_backend_2023_q3::startup::Application;
-use rinha_backend_2023_q3::{configuration, telemetry};
+use rinha_backend_2023_q3::configuration::StaticConfiguration;
+use rinha_backend_2023_q3::events::PersonListeners;
+use rinha_backend_2023_q3::export::ExportFormat;
+use rinha_backend_2023_q3::idempotency::IdempotencyStore;
+use rinha_backend_2023_q3::normalization::StackAliases;
+use rinha_backend_2023_q3::search_index::SearchIndex;
+use rinha_backend_2023_q3::startup::{get_client, get_database_connection, Application};
+use rinha_backend_2023_q3::storage::PersonStore;
+use rinha_backend_2023_q3::structs::{api, person};
+use rinha_backend_2023_q3::validation::BirthDateRules;
+use rinha_backend_2023_q3::{
+    admin, configuration, export, import, migrations, storage, telemetry, tenancy,
+};
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[command(name = "rest-api-server")]
+struct Cli {
+    /// Serves the API when missing
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The tenant to work on, required when tenants are configured
+    #[arg(long, global = true)]
+    tenant: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serves the API
+    Serve,
+    /// Creates the collections and indexes the server relies on and upgrades stored developers
+    Migrate,
+    /// Loads the configuration and prints it as JSON, with secrets redacted
+    CheckConfig,
+    /// Prints how many developers there are, soft-deleted ones aside
+    Count,
+    /// Prints a developer as JSON
+    Get { id: Uuid },
+    /// Drops the `test-<ulid>` databases left behind by the API tests
+    PurgeTestDbs {
+        /// Only lists the databases
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Imports developers from a NDJSON file, one person body per line
+    ///
+    /// Also indexes them when the search index is configured, which needs its writer lock: stop
+    /// any server using the same index first.
+    Import {
+        path: PathBuf,
+        #[arg(long, default_value_t = import::DEFAULT_BATCH_SIZE)]
+        batch_size: usize,
+    },
+    /// Exports every developer as NDJSON, CSV or Parquet
+    Export {
+        #[arg(long, default_value = "ndjson")]
+        format: ExportFormat,
+        /// Defaults to stdout; required for parquet
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Rebuilds the embedded search index from the stored developers
+    ///
+    /// Needs the index writer lock: stop any server using the same index first.
+    Reindex,
+}
+
+/// The developers of the tenant the command works on.
+async fn person_store(
+    static_config: &StaticConfiguration,
+    tenant: Option<&str>,
+) -> Result<PersonStore, Error> {
+    let database_config = static_config.database_for(tenant).map_err(Error::other)?;
+    let client = get_database_connection(database_config.clone())
+        .await
+        .expect("failed to connect to mongodb");
+    Ok(PersonStore::new(
+        client,
+        database_config.collection_name,
+        Arc::new(StackAliases::new(&static_config.stack_aliases)),
+    ))
+}
+
+/// The search index of the tenant the command works on, taking its writer lock.
+fn open_search_index(path: &std::path::Path, tenant: Option<&str>) -> Result<SearchIndex, Error> {
+    SearchIndex::open(&tenancy::tenant_path(path, tenant)).map_err(|error| match error {
+        TantivyError::LockFailure(LockError::LockBusy, _) => Error::other(
+            "the search index is locked, likely by a running server; stop it and retry",
+        ),
+        error => Error::other(error),
+    })
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    let subscriber = telemetry::get_subscriber(
-        "rinha-de-backend-2023-q3",
-        EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("info")),
-        std::io::stdout,
-    );
-    telemetry::init_subscriber(subscriber);
-
-    let static_config = configuration::get_static_configuration().expect("failed to load configs");
-    Application::build(static_config).await.run().await
+    let cli = Cli::parse();
+    let name = "rinha-de-backend-2023-q3";
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or(EnvFilter::new("info"));
+    // Subcommands print their results to stdout, so their telemetry goes to stderr.
+    if matches!(cli.command, None | Some(Command::Serve)) {
+        telemetry::init_subscriber(telemetry::get_subscriber(name, env_filter, std::io::stdout));
+    } else {
+        telemetry::init_subscriber(telemetry::get_subscriber(name, env_filter, std::io::stderr));
+    }
+
+    let static_config = configuration::get_static_configuration().map_err(Error::other)?;
+    let tenant = cli.tenant.as_deref();
+    match cli.command {
+        None | Some(Command::Serve) => Application::build(static_config).await.run().await,
+        Some(Command::Migrate) => {
+            let client = person_store(&static_config, tenant).await?;
+            let idempotency =
+                IdempotencyStore::new(client.database().clone(), &static_config.idempotency);
+            let ran = admin::migrate(&client, &idempotency)
+                .await
+                .map_err(Error::other)?;
+            for name in &ran {
+                println!("applied {}", name);
+            }
+            println!(
+                "migrated {} to schema version {}",
+                client.database().name(),
+                migrations::CURRENT_SCHEMA_VERSION
+            );
+            Ok(())
+        }
+        Some(Command::CheckConfig) => {
+            println!("{}", serde_json::to_string_pretty(&static_config)?);
+            Ok(())
+        }
+        Some(Command::Count) => {
+            let client = person_store(&static_config, tenant).await?;
+            let count = storage::count_persons(&client)
+                .await
+                .map_err(Error::other)?;
+            println!("{}", count);
+            Ok(())
+        }
+        Some(Command::Get { id }) => {
+            let client = person_store(&static_config, tenant).await?;
+            match storage::find_person(&client, id)
+                .await
+                .map_err(Error::other)?
+            {
+                Some(dev) => {
+                    let body = api::PersonBody::from(dev);
+                    println!("{}", serde_json::to_string_pretty(&body)?);
+                    Ok(())
+                }
+                None => Err(Error::other(format!("no developer with id {}", id))),
+            }
+        }
+        Some(Command::PurgeTestDbs { dry_run }) => {
+            let client = get_client(&static_config.database)
+                .await
+                .expect("failed to connect to mongodb");
+            let names = admin::purge_test_databases(&client, dry_run)
+                .await
+                .map_err(Error::other)?;
+            for name in &names {
+                println!("{}", name);
+            }
+            let verb = if dry_run { "would drop" } else { "dropped" };
+            println!("{} {} test databases", verb, names.len());
+            Ok(())
+        }
+        Some(Command::Import { path, batch_size }) => {
+            let stack_aliases = StackAliases::new(&static_config.stack_aliases);
+            let search_index = match &static_config.search_index {
+                Some(search_index_config) => Some(Arc::new(open_search_index(
+                    &search_index_config.path,
+                    tenant,
+                )?)),
+                None => None,
+            };
+            let client = person_store(&static_config, tenant).await?;
+            let file = tokio::fs::File::open(path).await?;
+            // Outbox events are stored, and relayed to the sinks and webhooks by a running server.
+            let listeners = PersonListeners {
+                search_index,
+                feed: None,
+                outbox: None,
+            };
+            let report = import::import_persons(
+                &client,
+                &stack_aliases,
+                &BirthDateRules::new(&static_config.birth_date),
+                &listeners,
+                tokio::io::BufReader::new(file),
+                batch_size.max(1),
+            )
+            .await?;
+            if let Some(search_index) = &listeners.search_index {
+                search_index.commit().map_err(Error::other)?;
+            }
+            for failure in report.lines.iter().filter(|result| result.error.is_some()) {
+                eprintln!(
+                    "line {}: {}",
+                    failure.line,
+                    failure.error.as_deref().unwrap_or_default()
+                );
+            }
+            println!(
+                "imported {} persons, {} failed",
+                report.inserted, report.failed
+            );
+            Ok(())
+        }
+        Some(Command::Export { format, output }) => {
+            let client = person_store(&static_config, tenant).await?;
+            let cursor = client
+                .stored_persons()
+                .find(person::not_deleted(), None)
+                .await
+                .map_err(Error::other)?;
+            let persons = migrations::read_persons(&client, cursor);
+            let sink: Box<dyn Write + Send> = match (&output, format) {
+                (Some(path), _) => Box::new(std::fs::File::create(path)?),
+                (None, ExportFormat::Parquet) => {
+                    return Err(Error::other("parquet exports require --output"))
+                }
+                (None, _) => Box::new(std::io::stdout()),
+            };
+            let mut sink = std::io::BufWriter::new(sink);
+
+            if format == ExportFormat::Parquet {
+                return export::write_parquet(persons, sink)
+                    .await
+                    .map_err(Error::other);
+            }
+            if let Some(header) = format.header() {
+                sink.write_all(&header)?;
+            }
+            let mut rows = persons.map_err(Error::other);
+            while let Some(dev) = rows.try_next().await? {
+                sink.write_all(&format.encode(dev)?)?;
+            }
+            sink.flush()
+        }
+        Some(Command::Reindex) => {
+            let Some(search_index_config) = &static_config.search_index else {
+                return Err(Error::other("the search index is not configured"));
+            };
+            let search_index = open_search_index(&search_index_config.path, tenant)?;
+            let client = person_store(&static_config, tenant).await?;
+            let indexed = search_index.rebuild(&client).await.map_err(Error::other)?;
+            println!("indexed {} persons", indexed);
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,276 @@
+use futures::future;
+use futures::stream::{Stream, TryStreamExt};
+use mongodb::bson::{doc, Bson, DateTime, Document};
+use mongodb::{Collection, Cursor};
+
+use crate::normalization::{fold_text, StackAliases};
+use crate::storage::{self, PersonStore};
+use crate::structs::person;
+
+/// Where applied migrations are recorded, one document per version.
+pub const MIGRATIONS_COLLECTION: &str = "_migrations";
+
+/// The version of the documents written by this build, see [`MIGRATIONS`].
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+/// A change to the shape of stored persons. Documents without a `schema_version` are at version 0.
+pub struct Migration {
+    /// What a document is at once upgraded by this migration.
+    pub version: u32,
+    pub name: &'static str,
+    /// Upgrades a document from the previous version. Must cope with fields of unexpected types.
+    pub upgrade: fn(&mut Document, &StackAliases),
+}
+
+/// Every migration, oldest first. New ones go last, with [`CURRENT_SCHEMA_VERSION`] bumped to
+/// their version; released ones are never changed.
+pub const MIGRATIONS: [Migration; 3] = [
+    Migration {
+        version: 1,
+        name: "fold_search_terms",
+        upgrade: fold_search_terms,
+    },
+    Migration {
+        version: 2,
+        name: "stack_entries",
+        upgrade: stack_entries,
+    },
+    Migration {
+        version: 3,
+        name: "canonical_stacks",
+        upgrade: canonical_stacks,
+    },
+];
+
+/// Persons stored before accent-insensitive search lack their folded terms.
+fn fold_search_terms(stored: &mut Document, _: &StackAliases) {
+    if stored.contains_key("search_terms") {
+        return;
+    }
+    let mut terms: Vec<Bson> = ["nickname", "name"]
+        .into_iter()
+        .filter_map(|field| stored.get_str(field).ok())
+        .map(|term| Bson::String(fold_text(term)))
+        .collect();
+    for field in ["stacks", "stack_labels"] {
+        terms.extend(strings(stored, field).map(|term| Bson::String(fold_text(term))));
+    }
+    stored.insert("search_terms", terms);
+}
+
+/// Persons stored before stack details lack an entry per stack.
+fn stack_entries(stored: &mut Document, _: &StackAliases) {
+    let has_stacks = matches!(stored.get("stacks"), Some(Bson::Array(_)));
+    if stored.contains_key("stack_entries") || !has_stacks {
+        return;
+    }
+    let labels: Vec<&str> = strings(stored, "stack_labels").collect();
+    let entries: Vec<Bson> = strings(stored, "stacks")
+        .enumerate()
+        .map(|(position, stack)| {
+            Bson::Document(doc! {
+                "stack": stack,
+                "label": labels.get(position).copied().unwrap_or(stack),
+            })
+        })
+        .collect();
+    stored.insert("stack_entries", entries);
+}
+
+/// Persons stored before stack aliases kept their stacks as submitted and no labels. The
+/// submitted stacks become the labels, and the stacks, entries and search terms use the
+/// canonical names instead.
+fn canonical_stacks(stored: &mut Document, stack_aliases: &StackAliases) {
+    let has_stacks = matches!(stored.get("stacks"), Some(Bson::Array(_)));
+    if stored.contains_key("stack_labels") || !has_stacks {
+        return;
+    }
+    let labels: Vec<String> = strings(stored, "stacks").map(String::from).collect();
+    let stacks: Vec<String> = labels
+        .iter()
+        .map(|label| stack_aliases.canonicalize(label))
+        .collect();
+    if let Ok(entries) = stored.get_array_mut("stack_entries") {
+        for entry in entries.iter_mut() {
+            if let Bson::Document(entry) = entry {
+                if let Ok(stack) = entry.get_str("stack") {
+                    let stack = stack_aliases.canonicalize(stack);
+                    entry.insert("stack", stack);
+                }
+            }
+        }
+    }
+    let terms: Vec<Bson> = ["nickname", "name"]
+        .into_iter()
+        .filter_map(|field| stored.get_str(field).ok())
+        .chain(stacks.iter().map(String::as_str))
+        .chain(labels.iter().map(String::as_str))
+        .map(|term| Bson::String(fold_text(term)))
+        .collect();
+    stored.insert("search_terms", terms);
+    stored.insert("stacks", stacks);
+    stored.insert("stack_labels", labels);
+}
+
+fn strings<'a>(stored: &'a Document, field: &str) -> impl Iterator<Item = &'a str> {
+    stored
+        .get_array(field)
+        .into_iter()
+        .flatten()
+        .filter_map(Bson::as_str)
+}
+
+fn as_version(value: &Bson) -> Option<u32> {
+    match value {
+        Bson::Int32(version) => u32::try_from(*version).ok(),
+        Bson::Int64(version) => u32::try_from(*version).ok(),
+        _ => None,
+    }
+}
+
+pub fn schema_version(stored: &Document) -> u32 {
+    stored
+        .get("schema_version")
+        .and_then(as_version)
+        .unwrap_or(0)
+}
+
+fn apply(stored: &mut Document, migration: &Migration, stack_aliases: &StackAliases) {
+    (migration.upgrade)(stored, stack_aliases);
+    stored.insert("schema_version", i64::from(migration.version));
+}
+
+/// Runs the migrations a stored person predates, returning whether there were any.
+pub fn upgrade(stored: &mut Document, stack_aliases: &StackAliases) -> bool {
+    let version = schema_version(stored);
+    let pending = MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > version);
+    let mut upgraded = false;
+    for migration in pending {
+        apply(stored, migration, stack_aliases);
+        upgraded = true;
+    }
+    upgraded
+}
+
+/// Matches the documents at a version below `version`, the unversioned ones included.
+fn older_than(version: u32) -> Document {
+    doc! {"schema_version": {"$not": {"$gte": i64::from(version)}}}
+}
+
+/// The update turning `before` into `after`, touching no other field so that concurrent writes
+/// to them are kept.
+fn changes(before: &Document, after: &Document) -> Document {
+    let set: Document = after
+        .iter()
+        .filter(|(key, value)| before.get(key.as_str()) != Some(*value))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    let unset: Document = before
+        .keys()
+        .filter(|key| !after.contains_key(key.as_str()))
+        .map(|key| (key.clone(), Bson::String(String::new())))
+        .collect();
+    let mut update = doc! {"$set": set};
+    if !unset.is_empty() {
+        update.insert("$unset", unset);
+    }
+    update
+}
+
+/// A stored person, upgraded in memory when it predates a migration.
+pub fn read_person(
+    mut stored: Document,
+    stack_aliases: &StackAliases,
+) -> Result<person::Person, mongodb::error::Error> {
+    upgrade(&mut stored, stack_aliases);
+    Ok(mongodb::bson::from_document(stored)?)
+}
+
+/// Like [`read_person`], also writing the upgrade back unless another one got there first.
+pub async fn read_and_store_person(
+    client: &PersonStore,
+    stored: Document,
+) -> Result<person::Person, mongodb::error::Error> {
+    let mut upgraded = stored.clone();
+    if upgrade(&mut upgraded, client.stack_aliases()) {
+        let mut filter = older_than(schema_version(&stored) + 1);
+        filter.insert("_id", stored.get("_id").cloned().unwrap_or(Bson::Null));
+        client
+            .stored_persons()
+            .update_one(filter, changes(&stored, &upgraded), None)
+            .await?;
+    }
+    Ok(mongodb::bson::from_document(upgraded)?)
+}
+
+/// The persons of a cursor over [`PersonStore::stored_persons`] of `client`, each read with
+/// [`read_person`].
+pub fn read_persons(
+    client: &PersonStore,
+    cursor: Cursor<Document>,
+) -> impl Stream<Item = Result<person::Person, mongodb::error::Error>> {
+    let stack_aliases = client.stack_aliases().clone();
+    cursor.and_then(move |stored| future::ready(read_person(stored, &stack_aliases)))
+}
+
+/// Applies the migrations not recorded in [`MIGRATIONS_COLLECTION`] to every stored person,
+/// returning their names. Safe to run concurrently with the server and with itself.
+pub async fn run(client: &PersonStore) -> Result<Vec<&'static str>, mongodb::error::Error> {
+    let migrations_store: Collection<Document> =
+        client.database().collection(MIGRATIONS_COLLECTION);
+    let applied: Vec<u32> = migrations_store
+        .distinct("_id", None, None)
+        .await?
+        .iter()
+        .filter_map(as_version)
+        .collect();
+
+    let devs_store = client.stored_persons();
+    let mut ran = vec![];
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+    {
+        let mut cursor = devs_store.find(older_than(migration.version), None).await?;
+        let mut upgraded: u64 = 0;
+        while let Some(stored) = cursor.try_next().await? {
+            let mut filter = older_than(migration.version);
+            filter.insert("_id", stored.get("_id").cloned().unwrap_or(Bson::Null));
+            // Documents still a few versions behind get the earlier steps they missed first.
+            let mut migrated = stored.clone();
+            for step in MIGRATIONS
+                .iter()
+                .filter(|step| step.version > schema_version(&stored))
+                .take_while(|step| step.version <= migration.version)
+            {
+                apply(&mut migrated, step, client.stack_aliases());
+            }
+            let updated = devs_store
+                .update_one(filter, changes(&stored, &migrated), None)
+                .await?;
+            upgraded += updated.modified_count;
+        }
+
+        let recorded = migrations_store
+            .insert_one(
+                doc! {
+                    "_id": i64::from(migration.version),
+                    "name": migration.name,
+                    "upgraded": upgraded as i64,
+                    "applied_at": DateTime::now(),
+                },
+                None,
+            )
+            .await;
+        match recorded {
+            Err(error) if storage::is_duplicate_key(&error) => {}
+            result => {
+                result?;
+                ran.push(migration.name);
+            }
+        }
+    }
+    Ok(ran)
+}
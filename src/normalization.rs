@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::configuration::StackAliasConfiguration;
+
+/// Trims, composes (Unicode NFC) and case-folds a stack name so that spelling variants compare equal.
+pub fn normalize_stack(stack: &str) -> String {
+    stack.trim().nfc().collect::<String>().to_lowercase()
+}
+
+/// Strips diacritics and case-folds `text`, so "João" and "JOAO" both fold to "joao".
+pub fn fold_text(text: &str) -> String {
+    text.nfd()
+        .filter(|character| !is_combining_mark(*character))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Escapes regex metacharacters so `term` is matched literally by a MongoDB `$regex`.
+pub fn escape_regex(term: &str) -> String {
+    term.chars().fold(String::new(), |mut escaped, character| {
+        if "\\^$.|?*+()[]{}".contains(character) {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+        escaped
+    })
+}
+
+/// Maps normalized stack spellings to their canonical name, e.g. "nodejs" and "node" to "node.js".
+#[derive(Clone, Debug, Default)]
+pub struct StackAliases {
+    canonical_by_alias: HashMap<String, String>,
+}
+
+impl StackAliases {
+    pub fn new(configured_aliases: &[StackAliasConfiguration]) -> Self {
+        let mut canonical_by_alias = HashMap::new();
+        for configured in configured_aliases {
+            let canonical = normalize_stack(&configured.canonical);
+            for alias in &configured.aliases {
+                canonical_by_alias.insert(normalize_stack(alias), canonical.clone());
+            }
+        }
+        StackAliases { canonical_by_alias }
+    }
+
+    pub fn canonicalize(&self, stack: &str) -> String {
+        let normalized = normalize_stack(stack);
+        match self.canonical_by_alias.get(&normalized) {
+            Some(canonical) => canonical.clone(),
+            None => normalized,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use utoipa::OpenApi;
+
+use crate::export::ExportFormat;
+use crate::routes;
+use crate::structs::api;
+
+/// The OpenAPI document of every route, built from the `#[utoipa::path]` of its handler. The
+/// `/v1` mirrors of the unversioned routes are left out.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Rinha de Backend 2023 Q3",
+        description = "Every unversioned route is also served under `/v1`."
+    ),
+    paths(
+        routes::devs::get_person,
+        routes::devs::delete_person,
+        routes::devs::restore_person,
+        routes::avatars::put_avatar,
+        routes::avatars::get_avatar,
+        routes::devs::create_person,
+        routes::devs::search_persons,
+        routes::import_devs::import_persons,
+        routes::export_devs::export_persons,
+        routes::person_stream::stream_persons,
+        routes::live_search::live_search,
+        routes::count_devs::count_persons,
+        routes::stacks::suggest_stacks,
+        routes::stacks::top_stacks,
+        routes::webhooks::create_webhook,
+        routes::webhooks::list_webhooks,
+        routes::webhooks::get_webhook,
+        routes::webhooks::update_webhook,
+        routes::webhooks::delete_webhook,
+        routes::webhooks::list_deliveries,
+        routes::webhooks::list_dead_letters,
+        routes::v2::get_person,
+        routes::v2::delete_person,
+        routes::v2::restore_person,
+        routes::v2::create_person,
+        routes::v2::search_persons,
+        routes::health_check::health_check,
+    ),
+    components(schemas(
+        api::CreatePersonBody,
+        api::PersonBody,
+        api::ScoredPersonBody,
+        api::EnglishPersonBody,
+        api::EnglishScoredPersonBody,
+        api::SearchSort,
+        api::Seniority,
+        api::StackInput,
+        api::StackEntryBody,
+        api::StackLevel,
+        ExportFormat,
+        api::PersonCountBody,
+        api::StackCountBody,
+        api::LiveSearchRequest,
+        api::LiveSearchEvent,
+        api::CreateWebhookBody,
+        api::WebhookBody,
+        api::WebhookPayload,
+        api::WebhookDeliveryLogBody,
+        api::WebhookDeadLetterBody,
+        api::PersonEventBody,
+        api::PersonEnvelope,
+        api::EnglishPersonEnvelope,
+        api::PersonPageEnvelope,
+        api::EnglishPersonPageEnvelope,
+        api::PageMeta,
+        api::ErrorBody,
+        api::ErrorDetail,
+    ))
+)]
+pub struct ApiDoc;
@@ -0,0 +1,320 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument};
+use mongodb::{Collection, IndexModel};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, Notify};
+
+use crate::configuration::{OutboxConfiguration, OutboxSinkConfiguration};
+use crate::migrations;
+use crate::storage::PersonStore;
+use crate::structs::{api, person};
+
+pub type SinkError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Somewhere person events are published to. `publish` only returns once the event is accepted,
+/// since the relay removes it from the outbox right after.
+pub trait EventSink: Send + Sync {
+    fn publish<'a>(
+        &'a self,
+        event: &'a api::PersonEventBody,
+    ) -> BoxFuture<'a, Result<(), SinkError>>;
+}
+
+pub fn sink_from_configuration(sink_config: &OutboxSinkConfiguration) -> Arc<dyn EventSink> {
+    match sink_config {
+        OutboxSinkConfiguration::Log => Arc::new(LogSink),
+        OutboxSinkConfiguration::Http { url } => Arc::new(HttpSink::new(url.clone())),
+        OutboxSinkConfiguration::Nats { address, subject } => {
+            Arc::new(NatsSink::new(address.clone(), subject.clone()))
+        }
+    }
+}
+
+pub struct LogSink;
+
+impl EventSink for LogSink {
+    fn publish<'a>(
+        &'a self,
+        event: &'a api::PersonEventBody,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            tracing::info!(event = %serde_json::to_string(event)?, "Publishing a person event");
+            Ok(())
+        })
+    }
+}
+
+/// POSTs every event as JSON, accepting any successful status as an acknowledgement.
+pub struct HttpSink {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl HttpSink {
+    pub fn new(url: String) -> Self {
+        HttpSink {
+            url,
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+impl EventSink for HttpSink {
+    fn publish<'a>(
+        &'a self,
+        event: &'a api::PersonEventBody,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            self.http
+                .post(&self.url)
+                .json(event)
+                .send()
+                .await?
+                .error_for_status()?;
+            Ok(())
+        })
+    }
+}
+
+/// Publishes events with the NATS text protocol, waiting for the `PONG` to a `PING` sent after
+/// every `PUB` so that the server has processed it before the event leaves the outbox.
+pub struct NatsSink {
+    address: String,
+    subject: String,
+    connection: Mutex<Option<BufStream<TcpStream>>>,
+}
+
+impl NatsSink {
+    pub fn new(address: String, subject: String) -> Self {
+        NatsSink {
+            address,
+            subject,
+            connection: Mutex::new(None),
+        }
+    }
+
+    async fn connect(&self) -> Result<BufStream<TcpStream>, SinkError> {
+        let mut connection = BufStream::new(TcpStream::connect(&self.address).await?);
+        let info = read_line(&mut connection).await?;
+        if !info.starts_with("INFO") {
+            return Err(format!("unexpected NATS greeting: {}", info).into());
+        }
+        connection
+            .write_all(b"CONNECT {\"verbose\":false,\"pedantic\":false}\r\n")
+            .await?;
+        Ok(connection)
+    }
+
+    async fn publish_on(
+        &self,
+        connection: &mut BufStream<TcpStream>,
+        payload: &[u8],
+    ) -> Result<(), SinkError> {
+        connection
+            .write_all(format!("PUB {} {}\r\n", self.subject, payload.len()).as_bytes())
+            .await?;
+        connection.write_all(payload).await?;
+        connection.write_all(b"\r\nPING\r\n").await?;
+        connection.flush().await?;
+        loop {
+            let line = read_line(connection).await?;
+            match line.as_str() {
+                "PONG" => return Ok(()),
+                "PING" => {
+                    connection.write_all(b"PONG\r\n").await?;
+                    connection.flush().await?;
+                }
+                line if line.starts_with("-ERR") => {
+                    return Err(format!("NATS error: {}", line).into())
+                }
+                // `+OK` and `INFO` updates.
+                _ => {}
+            }
+        }
+    }
+}
+
+impl EventSink for NatsSink {
+    fn publish<'a>(
+        &'a self,
+        event: &'a api::PersonEventBody,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            let payload = serde_json::to_vec(event)?;
+            let mut connection = self.connection.lock().await;
+            let mut stream = match connection.take() {
+                Some(stream) => stream,
+                None => self.connect().await?,
+            };
+            let published = self.publish_on(&mut stream, &payload).await;
+            // A connection that failed is in an unknown state; the next event reconnects.
+            if published.is_ok() {
+                *connection = Some(stream);
+            }
+            published
+        })
+    }
+}
+
+async fn read_line(connection: &mut BufStream<TcpStream>) -> Result<String, SinkError> {
+    let mut line = String::new();
+    if connection.read_line(&mut line).await? == 0 {
+        return Err("NATS connection closed".into());
+    }
+    Ok(line.trim_end().to_string())
+}
+
+/// Drains the outboxes of stored persons into sinks, removing each event only after every sink
+/// accepted it, so every event is published at least once even if the server stops halfway.
+pub struct OutboxRelay {
+    client: PersonStore,
+    sinks: Vec<Arc<dyn EventSink>>,
+    wake: Arc<Notify>,
+    poll_interval: Duration,
+}
+
+impl OutboxRelay {
+    pub fn new(
+        client: PersonStore,
+        sinks: Vec<Arc<dyn EventSink>>,
+        outbox_config: &OutboxConfiguration,
+    ) -> Self {
+        OutboxRelay {
+            client,
+            sinks,
+            wake: Arc::new(Notify::new()),
+            poll_interval: Duration::from_millis(outbox_config.poll_interval_ms),
+        }
+    }
+
+    /// Notified whenever persons are stored with new events.
+    pub fn waker(&self) -> Arc<Notify> {
+        self.wake.clone()
+    }
+
+    fn devs_store(&self) -> Collection<person::Person> {
+        self.client.persons()
+    }
+
+    pub async fn run(self) {
+        if let Err(error) = ensure_index(&self.client).await {
+            println!("outbox: {}", error);
+        }
+
+        loop {
+            match self.relay_next().await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(error) => println!("outbox: {}", error),
+            }
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    /// Publishes `event` to every sink in turn; after a failure the event is published again to
+    /// all of them.
+    async fn publish(&self, event: &api::PersonEventBody) -> Result<(), SinkError> {
+        for sink in &self.sinks {
+            sink.publish(event).await?;
+        }
+        Ok(())
+    }
+
+    /// Publishes the events of one person with a non-empty outbox, returning whether there was
+    /// one.
+    async fn relay_next(&self) -> Result<bool, SinkError> {
+        let now = DateTime::now();
+        let locked_until = DateTime::from_millis(
+            now.timestamp_millis() + 10 * self.poll_interval.as_millis() as i64,
+        );
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let Some(stored) = self
+            .client
+            .stored_persons()
+            .find_one_and_update(
+                doc! {
+                    "outbox.id": {"$exists": true},
+                    "$or": [
+                        {"outbox_locked_until": null},
+                        {"outbox_locked_until": {"$lt": now}},
+                    ],
+                },
+                doc! {"$set": {"outbox_locked_until": locked_until}},
+                options,
+            )
+            .await?
+        else {
+            return Ok(false);
+        };
+        let dev = migrations::read_person(stored, self.client.stack_aliases())?;
+
+        for event in dev.outbox.clone().unwrap_or_default() {
+            let published = self
+                .publish(&api::PersonEventBody {
+                    id: event.id,
+                    event: event.event.clone(),
+                    occurred_at: event.occurred_at.to_chrono(),
+                    person: api::PersonBody::from(dev.clone()),
+                })
+                .await;
+            if let Err(error) = published {
+                // Keep the lock until the retry is due, so a failing sink is not hammered.
+                let retry_at = DateTime::from_millis(
+                    DateTime::now().timestamp_millis() + self.poll_interval.as_millis() as i64,
+                );
+                self.devs_store()
+                    .update_one(
+                        doc! {"_id": dev.id},
+                        doc! {"$set": {"outbox_locked_until": retry_at}},
+                        None,
+                    )
+                    .await?;
+                return Err(error);
+            }
+            self.devs_store()
+                .update_one(
+                    doc! {"_id": dev.id},
+                    doc! {"$pull": {"outbox": {"id": event.id}}},
+                    None,
+                )
+                .await?;
+        }
+
+        self.devs_store()
+            .update_one(
+                doc! {"_id": dev.id, "outbox": {"$size": 0}},
+                doc! {"$unset": {"outbox": "", "outbox_locked_until": ""}},
+                None,
+            )
+            .await?;
+        self.devs_store()
+            .update_one(
+                doc! {"_id": dev.id, "outbox.id": {"$exists": true}},
+                doc! {"$unset": {"outbox_locked_until": ""}},
+                None,
+            )
+            .await?;
+        Ok(true)
+    }
+}
+
+/// Indexes the persons with pending events, keeping the relay lookups cheap.
+pub async fn ensure_index(client: &PersonStore) -> Result<(), mongodb::error::Error> {
+    let pending_index = IndexModel::builder()
+        .keys(doc! {"outbox.id": 1})
+        .options(IndexOptions::builder().sparse(true).build())
+        .build();
+    let devs_store = client.persons();
+    devs_store.create_index(pending_index, None).await?;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Bson, DateTime};
+use uuid::Uuid;
+
+use crate::avatars::Avatars;
+use crate::configuration::SoftDeleteConfiguration;
+use crate::migrations;
+use crate::search_index::{self, SearchIndex};
+use crate::storage::PersonStore;
+use crate::structs::person;
+
+/// Hard-deletes the persons soft-deleted longer ago than the retention, freeing their nicknames.
+pub struct PurgeJob {
+    client: PersonStore,
+    search_index: Option<Arc<SearchIndex>>,
+    avatars: Arc<Avatars>,
+    retention: Duration,
+    interval: Duration,
+}
+
+impl PurgeJob {
+    pub fn new(
+        client: PersonStore,
+        search_index: Option<Arc<SearchIndex>>,
+        avatars: Arc<Avatars>,
+        soft_delete_config: &SoftDeleteConfiguration,
+    ) -> Self {
+        PurgeJob {
+            client,
+            search_index,
+            avatars,
+            retention: Duration::from_secs(soft_delete_config.retention_seconds),
+            interval: Duration::from_millis(soft_delete_config.purge_interval_ms),
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            match self.purge().await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!(purged, "Purged deleted developers"),
+                Err(error) => println!("purge: {}", error),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    /// Returns how many persons were purged.
+    pub async fn purge(&self) -> Result<usize, mongodb::error::Error> {
+        let cutoff = DateTime::from_millis(
+            DateTime::now().timestamp_millis() - self.retention.as_millis() as i64,
+        );
+        let expired = doc! {"deleted_at": {"$lt": cutoff}};
+        let devs_store = self.client.stored_persons();
+        let expired_cursor = devs_store.find(expired.clone(), None).await?;
+        let expired_devs: Vec<person::Person> =
+            migrations::read_persons(&self.client, expired_cursor)
+                .try_collect()
+                .await?;
+        let ids: Vec<Uuid> = expired_devs.iter().map(|dev| dev.id).collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let in_ids = doc! {"$in": ids.iter().copied().map(Bson::from).collect::<Vec<Bson>>()};
+        let mut filter = expired;
+        filter.insert("_id", in_ids.clone());
+        // Persons restored in the meantime no longer match the filter and are kept.
+        let purged = devs_store.delete_many(filter, None).await?;
+        // Avatar blobs are only removed once their person is gone for good.
+        let kept_cursor = devs_store.find(doc! {"_id": in_ids}, None).await?;
+        let kept: Vec<Uuid> = migrations::read_persons(&self.client, kept_cursor)
+            .map_ok(|dev| dev.id)
+            .try_collect()
+            .await?;
+        let purged_devs: Vec<person::Person> = expired_devs
+            .into_iter()
+            .filter(|dev| !kept.contains(&dev.id))
+            .collect();
+        for dev in &purged_devs {
+            if let Some(avatar) = &dev.avatar {
+                if let Err(error) = self.avatars.remove(dev.id, &avatar.thumbnail_sizes).await {
+                    println!("purge: {}", error);
+                }
+            }
+        }
+        // Restored persons stay searchable.
+        let purged_ids = purged_devs.iter().map(|dev| dev.id).collect();
+        search_index::unindex_persons(self.search_index.clone(), purged_ids).await;
+        Ok(purged.deleted_count as usize)
+    }
+}
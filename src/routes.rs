@@ -1,3 +1,18 @@
+pub mod avatars;
 pub mod count_devs;
 pub mod devs;
+pub mod docs;
+pub mod export_devs;
 pub mod health_check;
+pub mod import_devs;
+pub mod live_search;
+pub mod person_stream;
+pub mod stacks;
+pub mod v2;
+pub mod webhooks;
+
+/// Whether `error`, or one of its sources, is a request body exceeding its length limit.
+pub(crate) fn exceeds_length_limit(error: &(dyn std::error::Error + 'static)) -> bool {
+    std::iter::successors(Some(error), |error| error.source())
+        .any(|error| error.is::<http_body_util::LengthLimitError>())
+}
@@ -0,0 +1,209 @@
+use crate::avatars::{self, Avatars, InvalidAvatar};
+use crate::routes;
+use crate::storage::{self, PersonStore};
+use crate::structs::{api, person};
+use axum::body::Body;
+use axum::extract::{Path, Query, State};
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use mongodb::bson::DateTime;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Replaces a person's avatar, keeping the original and a PNG thumbnail per configured size.
+#[utoipa::path(
+    put,
+    path = "/pessoas/{id}/avatar",
+    tag = "pessoas",
+    params(("id" = Uuid, Path, description = "Person id")),
+    request_body(content = Vec<u8>, description = "A PNG, JPEG or WebP image", content_type = "image/*"),
+    responses(
+        (status = 204, description = "Stored", headers(("ETag" = String))),
+        (status = 400, description = "Unreadable body"),
+        (status = 404, description = "No such person"),
+        (status = 413, description = "Image too large"),
+        (status = 415, description = "Not a PNG, JPEG or WebP image"),
+        (status = 422, description = "Undecodable image or dimensions too large"),
+    ),
+)]
+#[tracing::instrument(name = "Uploading an avatar", skip(client, avatars, body))]
+pub async fn put_avatar(
+    State(client): State<PersonStore>,
+    State(avatars): State<Arc<Avatars>>,
+    Path(id): Path<Uuid>,
+    body: Body,
+) -> Response {
+    let bytes = match axum::body::to_bytes(body, avatars.max_bytes()).await {
+        Ok(bytes) => bytes,
+        Err(error) if routes::exceeds_length_limit(&error) => {
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response()
+        }
+        Err(error) => {
+            println!("put avatar: {}", error);
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+    if avatars::sniff(&bytes).is_err() {
+        return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response();
+    }
+    let previous = match storage::find_person(&client, id).await {
+        Ok(Some(dev)) => dev.avatar,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            println!("put avatar: {}", error);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let processor = avatars.clone();
+    let processed =
+        match tokio::task::spawn_blocking(move || processor.process(bytes.to_vec())).await {
+            Ok(Ok(processed)) => processed,
+            Ok(Err(InvalidAvatar::UnsupportedFormat)) => {
+                return StatusCode::UNSUPPORTED_MEDIA_TYPE.into_response()
+            }
+            Ok(Err(error)) => {
+                println!("put avatar: {}", error);
+                return StatusCode::UNPROCESSABLE_ENTITY.into_response();
+            }
+            Err(error) => {
+                println!("put avatar: {}", error);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+    let avatar = person::Avatar {
+        content_type: processed.content_type.to_string(),
+        digest: processed.digest.clone(),
+        thumbnail_sizes: processed.thumbnails.iter().map(|(size, _)| *size).collect(),
+        updated_at: DateTime::now(),
+    };
+    if let Err(error) = avatars.store(id, processed).await {
+        println!("put avatar: {}", error);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    // Thumbnails of sizes no longer configured would otherwise be left behind.
+    if let Some(previous) = previous {
+        let stale = previous
+            .thumbnail_sizes
+            .into_iter()
+            .filter(|size| !avatar.thumbnail_sizes.contains(size))
+            .collect::<Vec<u32>>();
+        if let Err(error) = avatars.remove_thumbnails(id, &stale).await {
+            println!("put avatar: {}", error);
+        }
+    }
+    let status = match storage::set_avatar(&client, id, &avatar).await {
+        Ok(true) => {
+            return (
+                StatusCode::NO_CONTENT,
+                [(header::ETAG, avatars::etag(&avatar.digest, None))],
+            )
+                .into_response()
+        }
+        // Deleted since it was looked up.
+        Ok(false) => StatusCode::NOT_FOUND,
+        Err(error) => {
+            println!("put avatar: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    // No person refers to the stored images.
+    if let Err(error) = avatars.remove(id, &avatar.thumbnail_sizes).await {
+        println!("put avatar: {}", error);
+    }
+    status.into_response()
+}
+
+/// Sends a person's avatar, or one of its thumbnails, answering `If-None-Match` revalidations
+/// with a 304.
+#[utoipa::path(
+    get,
+    path = "/pessoas/{id}/avatar",
+    tag = "pessoas",
+    params(("id" = Uuid, Path, description = "Person id"), api::AvatarQuery),
+    responses(
+        (status = 200, description = "The image", content_type = "image/*", body = Vec<u8>,
+            headers(("ETag" = String), ("Cache-Control" = String), ("Last-Modified" = String))),
+        (status = 304, description = "Not modified since the given ETag"),
+        (status = 404, description = "No such person, avatar or thumbnail size"),
+    ),
+)]
+#[tracing::instrument(name = "Sending an avatar", skip(client, avatars, headers))]
+pub async fn get_avatar(
+    State(client): State<PersonStore>,
+    State(avatars): State<Arc<Avatars>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<api::AvatarQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let avatar = match storage::find_person(&client, id).await {
+        Ok(Some(person::Person {
+            avatar: Some(avatar),
+            ..
+        })) => avatar,
+        Ok(_) => return StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            println!("get avatar: {}", error);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let content_type = match query.size {
+        Some(size) if !avatar.thumbnail_sizes.contains(&size) => {
+            return StatusCode::NOT_FOUND.into_response()
+        }
+        Some(_) => avatars::THUMBNAIL_CONTENT_TYPE.to_string(),
+        None => avatar.content_type,
+    };
+
+    let etag = avatars::etag(&avatar.digest, query.size);
+    let caching = [
+        (header::ETAG, etag.clone()),
+        (
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", avatars.cache_max_age().as_secs()),
+        ),
+        (
+            header::LAST_MODIFIED,
+            avatar
+                .updated_at
+                .to_chrono()
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string(),
+        ),
+    ];
+    if matches_etag(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, caching).into_response();
+    }
+
+    match avatars.load(id, query.size).await {
+        Ok(Some(image)) => (
+            StatusCode::OK,
+            caching,
+            [(header::CONTENT_TYPE, content_type)],
+            image,
+        )
+            .into_response(),
+        Ok(None) => {
+            println!("get avatar: missing blob for {}", id);
+            StatusCode::NOT_FOUND.into_response()
+        }
+        Err(error) => {
+            println!("get avatar: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Whether `If-None-Match` lists `etag`, or is `*`.
+fn matches_etag(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
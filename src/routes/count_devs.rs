@@ -1,16 +1,51 @@
-use crate::structs::person;
+use crate::routes::stacks;
+use crate::storage::{self, PersonStore};
+use crate::structs::{api, person};
 use axum::extract::State;
 use axum::{
-    http::{header, StatusCode},
-    response::IntoResponse,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
 };
-use mongodb::{Collection, Database};
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Bson};
+use mongodb::Collection;
+use serde::Deserialize;
 
-pub async fn count_persons(State(client): State<Database>) -> impl IntoResponse {
-    let devs_store: Collection<person::Person> = client.collection("devs");
-    let found_dev = devs_store.count_documents(None, None).await;
+#[derive(Deserialize)]
+struct CountBucket {
+    #[serde(rename = "_id")]
+    key: Option<String>,
+    count: u64,
+}
+
+#[derive(Deserialize)]
+struct CountFacets {
+    total: Vec<CountBucket>,
+    by_stack: Vec<CountBucket>,
+    by_birth_year: Vec<CountBucket>,
+    by_creation_day: Vec<CountBucket>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/contagem-pessoas",
+    tag = "pessoas",
+    responses(
+        (status = 200, description = "The number of persons as plain text, or grouped with `Accept: application/json`", body = api::PersonCountBody),
+    ),
+)]
+pub async fn count_persons(State(client): State<PersonStore>, headers: HeaderMap) -> Response {
+    let devs_store = client.persons();
+    let wants_json = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| quality(accept, "application/json") > quality(accept, "text/plain"));
+    if wants_json {
+        return count_persons_by_group(devs_store).await.into_response();
+    }
 
-    match found_dev {
+    match storage::count_persons(&client).await {
         Ok(count) => Ok((
             StatusCode::OK,
             [(header::CONTENT_TYPE, String::from("text/plain"))],
@@ -21,4 +56,99 @@ pub async fn count_persons(State(client): State<Database>) -> impl IntoResponse
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
+    .into_response()
+}
+
+/// The q-value `accept` gives `media_type`, taken from the most specific range matching it, or 0
+/// when none does.
+fn quality(accept: &str, media_type: &str) -> f32 {
+    let wildcard = format!("{}/*", media_type.split('/').next().unwrap_or_default());
+    accept
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let range_type = parts.next()?.trim().to_ascii_lowercase();
+            let specificity = if range_type == media_type {
+                2
+            } else if range_type == wildcard {
+                1
+            } else if range_type == "*/*" {
+                0
+            } else {
+                return None;
+            };
+            let q = parts
+                .find_map(|parameter| parameter.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            Some((specificity, q))
+        })
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, q)| q)
+}
+
+async fn count_persons_by_group(devs_store: Collection<person::Person>) -> impl IntoResponse {
+    let count_by = |key: Bson| {
+        vec![
+            doc! {"$group": {"_id": key, "count": {"$sum": 1}}},
+            doc! {"$match": {"_id": {"$ne": null}}},
+        ]
+    };
+    // Grouped like the stack leaderboard, by canonical stack under its submitted spelling.
+    let mut by_stack = stacks::labeled_stacks();
+    by_stack.extend([
+        doc! {
+            "$group": {
+                "_id": "$canonical",
+                "label": {"$first": "$label"},
+                "count": {"$sum": 1},
+            }
+        },
+        doc! {"$project": {"_id": "$label", "count": 1}},
+    ]);
+    let pipeline = vec![
+        doc! {"$match": person::not_deleted()},
+        doc! {
+            "$facet": {
+                "total": [{"$count": "count"}],
+                "by_stack": by_stack,
+                "by_birth_year": count_by(doc! {"$substrCP": ["$birth_date", 0, 4]}.into()),
+                "by_creation_day": count_by(doc! {
+                    "$dateToString": {"format": "%Y-%m-%d", "date": "$created_at"}
+                }.into()),
+            }
+        },
+    ];
+
+    let aggregated = match devs_store.aggregate(pipeline, None).await {
+        Ok(mut cursor) => cursor.try_next().await,
+        Err(error) => Err(error),
+    };
+    let facets = aggregated.and_then(|facets| {
+        mongodb::bson::from_document::<CountFacets>(facets.unwrap_or_default())
+            .map_err(mongodb::error::Error::from)
+    });
+
+    match facets {
+        Ok(facets) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(api::PersonCountBody {
+                total: facets.total.first().map_or(0, |bucket| bucket.count),
+                by_stack: into_counts(facets.by_stack),
+                by_birth_year: into_counts(facets.by_birth_year),
+                by_creation_day: into_counts(facets.by_creation_day),
+            }),
+        )),
+        Err(error) => {
+            println!("count by group: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn into_counts(buckets: Vec<CountBucket>) -> std::collections::BTreeMap<String, u64> {
+    buckets
+        .into_iter()
+        .filter_map(|bucket| Some((bucket.key?, bucket.count)))
+        .collect()
 }
@@ -1,31 +1,42 @@
+use crate::events::PersonListeners;
+use crate::field_naming::FieldNaming;
+use crate::idempotency::{self, HashedJson, IdempotencyStore, Reservation};
+use crate::normalization::StackAliases;
+use crate::search;
+use crate::search_index::SearchIndex;
+use crate::storage::{self, PersonStore};
+use crate::structs::idempotency::StoredResponse;
 use crate::structs::{api, person};
+use crate::validation::{self, BirthDateRules, InvalidPerson};
 use axum::extract::{Path, Query, State};
 use axum::{
-    http::{header, StatusCode},
-    response::IntoResponse,
-    Json,
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
 };
-use futures::stream::TryStreamExt;
-use mongodb::{bson::doc, Collection, Database};
+use std::sync::Arc;
 use uuid::Uuid;
 
-#[tracing::instrument(name = "Looking for a developer", skip(client))]
-pub async fn get_person(State(client): State<Database>, Path(id): Path<Uuid>) -> impl IntoResponse {
-    let devs_store: Collection<person::Person> = client.collection("devs");
-    let found_dev = devs_store.find_one(doc! {"_id": id}, None).await;
-
-    match found_dev {
-        Ok(Some(dev)) => Ok((
-            StatusCode::OK,
-            [(header::CONTENT_TYPE, "application/json")],
-            Json(api::PersonBody {
-                id: dev.id,
-                name: dev.name,
-                nickname: dev.nickname,
-                birth_date: dev.birth_date,
-                stacks: dev.stacks,
-            }),
+#[utoipa::path(
+    get,
+    path = "/pessoas/{id}",
+    tag = "pessoas",
+    params(("id" = Uuid, Path, description = "Person id")),
+    responses(
+        (status = 200, content(
+            ("application/json" = api::PersonBody),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonBody),
         )),
+        (status = 404, description = "No such person"),
+    ),
+)]
+#[tracing::instrument(name = "Looking for a developer", skip(client))]
+pub async fn get_person(
+    State(client): State<PersonStore>,
+    Path(id): Path<Uuid>,
+    naming: FieldNaming,
+) -> impl IntoResponse {
+    match storage::find_person(&client, id).await {
+        Ok(Some(dev)) => Ok((StatusCode::OK, naming.render(api::PersonBody::from(dev)))),
         Ok(None) => Err(StatusCode::NOT_FOUND),
         Err(error) => {
             println!("get_by_id: {}", error);
@@ -34,104 +45,283 @@ pub async fn get_person(State(client): State<Database>, Path(id): Path<Uuid>) ->
     }
 }
 
-#[tracing::instrument(name = "Adding a new developer", skip(client, body))]
-pub async fn create_person(
-    State(client): State<Database>,
-    Json(body): Json<api::CreatePersonBody>,
-) -> impl IntoResponse {
-    let user = person::Person {
-        id: Uuid::new_v4(),
-        name: body.name,
-        nickname: body.nickname,
-        birth_date: body.birth_date,
-        stacks: body.stacks,
-    };
-    let devs_store: Collection<person::Person> = client.collection("devs");
-    let inserted_result = devs_store.insert_one(&user, None).await;
-    match inserted_result {
-        Ok(_) => Ok((
-            StatusCode::CREATED,
-            [
-                (header::LOCATION, format!("/pessoas/{}", &user.id)),
-                (header::CONTENT_TYPE, String::from("application/json")),
-            ],
-            Json(api::PersonBody {
-                id: user.id,
-                name: user.name,
-                nickname: user.nickname,
-                birth_date: user.birth_date,
-                stacks: user.stacks,
-            }),
+#[utoipa::path(
+    post,
+    path = "/pessoas",
+    tag = "pessoas",
+    request_body = api::CreatePersonBody,
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response to retries with the same key")),
+    responses(
+        (status = 201, headers(("Location" = String)), content(
+            ("application/json" = api::PersonBody),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonBody),
         )),
+        (status = 400, description = "Invalid body or idempotency key"),
+        (status = 409, description = "Idempotency key in use or reused with another body"),
+        (status = 422, description = "Invalid person or taken nickname"),
+    ),
+)]
+#[tracing::instrument(name = "Adding a new developer", skip(creator, headers, body))]
+pub async fn create_person(
+    State(creator): State<PersonCreator>,
+    headers: HeaderMap,
+    naming: FieldNaming,
+    HashedJson { value: body, hash }: HashedJson<api::CreatePersonBody>,
+) -> Response {
+    match creator.create(&headers, body, &hash).await {
+        Ok(created) if created.replayed => replay(created.response, naming),
+        Ok(created) => send_stored(created.response, naming),
+        Err(error) => error.status().into_response(),
+    }
+}
+
+/// A person created by [`PersonCreator::create`], or the response to its first request if it was a retry.
+pub struct Created {
+    pub response: StoredResponse,
+    pub replayed: bool,
+}
+
+#[derive(Debug)]
+pub enum CreateError {
+    InvalidIdempotencyKey,
+    InvalidPerson(InvalidPerson),
+    /// A request with the same key is still being handled.
+    IdempotencyKeyInUse,
+    /// The key was used for a request with a different body.
+    IdempotencyKeyReused,
+    NicknameTaken,
+    Internal,
+}
+
+impl CreateError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            CreateError::InvalidIdempotencyKey => StatusCode::BAD_REQUEST,
+            CreateError::IdempotencyKeyInUse | CreateError::IdempotencyKeyReused => {
+                StatusCode::CONFLICT
+            }
+            CreateError::InvalidPerson(_) | CreateError::NicknameTaken => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            CreateError::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Everything creating a person needs, shared by every API version, which only differ in how they
+/// render the outcome.
+#[derive(Clone)]
+pub struct PersonCreator {
+    pub client: PersonStore,
+    pub stack_aliases: Arc<StackAliases>,
+    pub birth_date_rules: Arc<BirthDateRules>,
+    pub listeners: PersonListeners,
+    pub idempotency: Arc<IdempotencyStore>,
+}
+
+impl PersonCreator {
+    /// Creates a person, honoring the request's `Idempotency-Key`.
+    pub async fn create(
+        &self,
+        headers: &HeaderMap,
+        body: api::CreatePersonBody,
+        hash: &str,
+    ) -> Result<Created, CreateError> {
+        let Ok(idempotency_key) = idempotency::idempotency_key(headers) else {
+            return Err(CreateError::InvalidIdempotencyKey);
+        };
+        validation::validate_person(&body, &self.birth_date_rules)
+            .map_err(CreateError::InvalidPerson)?;
+        if let Some(key) = &idempotency_key {
+            match self.idempotency.reserve(key, hash).await {
+                Ok(Reservation::Reserved) => {}
+                Ok(Reservation::Completed(response)) => {
+                    return Ok(Created {
+                        response,
+                        replayed: true,
+                    })
+                }
+                Ok(Reservation::InFlight) => return Err(CreateError::IdempotencyKeyInUse),
+                Ok(Reservation::Mismatch) => return Err(CreateError::IdempotencyKeyReused),
+                Err(error) => {
+                    println!("post: {}", error);
+                    return Err(CreateError::Internal);
+                }
+            }
+        }
+
+        let created = insert_person(&self.client, &self.stack_aliases, &self.listeners, body).await;
+        if let Some(key) = &idempotency_key {
+            let stored = match &created {
+                Ok(response) => self.idempotency.complete(key, response.clone()).await,
+                Err(_) => self.idempotency.release(key).await,
+            };
+            if let Err(error) = stored {
+                println!("post: {}", error);
+            }
+        }
+        created.map(|response| Created {
+            response,
+            replayed: false,
+        })
+    }
+}
+
+/// Stores a new person unless its nickname is taken, returning the response to send.
+async fn insert_person(
+    client: &PersonStore,
+    stack_aliases: &StackAliases,
+    listeners: &PersonListeners,
+    body: api::CreatePersonBody,
+) -> Result<StoredResponse, CreateError> {
+    let user = person::Person::new(body, stack_aliases);
+    match storage::insert_person(client, &user).await {
+        Ok(()) => {}
+        Err(storage::InsertPersonError::NicknameTaken) => return Err(CreateError::NicknameTaken),
         Err(error) => {
             println!("post: {}", error);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            return Err(CreateError::Internal);
         }
     }
+    listeners.created(vec![user.clone()]).await;
+
+    Ok(StoredResponse {
+        status: StatusCode::CREATED.as_u16(),
+        location: format!("/pessoas/{}", &user.id),
+        body: serde_json::to_string(&api::PersonBody::from(user))
+            .expect("person bodies always serialize"),
+    })
+}
+
+/// Sends a stored creation response, rendering its person with `naming`.
+fn send_stored(response: StoredResponse, naming: FieldNaming) -> Response {
+    let body = match naming.render_stored::<api::PersonBody>(response.body) {
+        Ok(body) => body,
+        Err(error) => {
+            println!("post: {}", error);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    (
+        StatusCode::from_u16(response.status).unwrap_or(StatusCode::OK),
+        [
+            (header::LOCATION, response.location),
+            (header::CONTENT_TYPE, naming.content_type().to_string()),
+        ],
+        body,
+    )
+        .into_response()
 }
 
-#[tracing::instrument(name = "Searching for a developer", skip(client))]
+fn replay(response: StoredResponse, naming: FieldNaming) -> Response {
+    let mut replayed = send_stored(response, naming);
+    replayed.headers_mut().insert(
+        HeaderName::from_static(idempotency::IDEMPOTENT_REPLAYED_HEADER),
+        HeaderValue::from_static("true"),
+    );
+    replayed
+}
+
+#[utoipa::path(
+    get,
+    path = "/pessoas",
+    tag = "pessoas",
+    params(api::SearchPersonQuery),
+    responses(
+        (status = 200, content(
+            ("application/json" = [api::ScoredPersonBody]),
+            ("application/vnd.rinha.en+json" = [api::EnglishScoredPersonBody]),
+        )),
+        (status = 400, description = "Invalid search"),
+    ),
+)]
+#[tracing::instrument(
+    name = "Searching for a developer",
+    skip(client, stack_aliases, search_index)
+)]
 pub async fn search_persons(
-    State(client): State<Database>,
+    State(client): State<PersonStore>,
+    State(stack_aliases): State<Arc<StackAliases>>,
+    State(search_index): State<Option<Arc<SearchIndex>>>,
     Query(query): Query<api::SearchPersonQuery>,
+    naming: FieldNaming,
 ) -> impl IntoResponse {
-    let devs_store: Collection<person::Person> = client.collection("devs");
-
-    let search_cursor = devs_store
-        .find(
-            doc! {
-                "$or": [
-                    {
-                        "name": mongodb::bson::Regex{
-                            pattern: query.search_term.clone(),
-                            options: String::from("i"),
-                        }
-                    },
-                    {
-                        "stacks": {
-                            "$in": [
-                                mongodb::bson::Regex{
-                                    pattern: query.search_term.clone(),
-                                    options: String::from("i"),
-                                }
-                            ]
-                        }
-                    },
-                    {
-                        "nickname": mongodb::bson::Regex{
-                            pattern: query.search_term.clone(),
-                            options: String::from("i"),
-                        }
-                    }
-                ]
-            },
-            None,
-        )
-        .await;
-    match search_cursor {
-        Ok(cursor) => {
-            let found_devs = cursor.try_collect().await.unwrap_or_else(|_| vec![]);
+    match search::find_persons(&client, &stack_aliases, search_index, &query).await {
+        Ok(found_devs) => {
+            let explain = query.explain.unwrap_or(false);
 
             Ok((
                 StatusCode::OK,
-                [(header::CONTENT_TYPE, "application/json")],
-                Json(
+                naming.render(
                     found_devs
-                        .iter()
-                        .map(|dev| api::PersonBody {
-                            id: dev.id,
-                            name: dev.name.clone(),
-                            nickname: dev.nickname.clone(),
-                            birth_date: dev.birth_date,
-                            stacks: dev.stacks.clone(),
+                        .into_iter()
+                        .map(|(dev, score)| api::ScoredPersonBody {
+                            person: api::PersonBody::from(dev),
+                            score: explain.then_some(score),
                         })
-                        .collect::<Vec<api::PersonBody>>(),
+                        .collect::<Vec<api::ScoredPersonBody>>(),
                 ),
             ))
         }
+        Err(error @ search::SearchError::Invalid(_)) => {
+            println!("persons?t=QUERY: {}", error);
+            Err(StatusCode::BAD_REQUEST)
+        }
         Err(error) => {
             println!("persons?t=QUERY: {}", error);
             Err(StatusCode::INTERNAL_SERVER_ERROR)
         }
     }
 }
+
+/// Hides a person until it is restored or purged; its nickname stays taken meanwhile.
+#[utoipa::path(
+    delete,
+    path = "/pessoas/{id}",
+    tag = "pessoas",
+    params(("id" = Uuid, Path, description = "Person id")),
+    responses(
+        (status = 204, description = "Soft-deleted"),
+        (status = 404, description = "No such person"),
+    ),
+)]
+#[tracing::instrument(name = "Deleting a developer", skip(client))]
+pub async fn delete_person(State(client): State<PersonStore>, Path(id): Path<Uuid>) -> StatusCode {
+    match storage::soft_delete_person(&client, id).await {
+        Ok(false) => StatusCode::NOT_FOUND,
+        Ok(true) => StatusCode::NO_CONTENT,
+        Err(error) => {
+            println!("delete: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/pessoas/{id}/restore",
+    tag = "pessoas",
+    params(("id" = Uuid, Path, description = "Person id")),
+    responses(
+        (status = 200, content(
+            ("application/json" = api::PersonBody),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonBody),
+        )),
+        (status = 404, description = "No such deleted person"),
+    ),
+)]
+#[tracing::instrument(name = "Restoring a developer", skip(client))]
+pub async fn restore_person(
+    State(client): State<PersonStore>,
+    Path(id): Path<Uuid>,
+    naming: FieldNaming,
+) -> impl IntoResponse {
+    match storage::restore_person(&client, id).await {
+        Ok(Some(dev)) => Ok((StatusCode::OK, naming.render(api::PersonBody::from(dev)))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(error) => {
+            println!("restore: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::openapi::ApiDoc;
+use axum::http::header;
+use axum::response::{Html, IntoResponse};
+use axum::Json;
+use utoipa::OpenApi;
+
+/// Swagger UI, loaded from a CDN at a pinned version, browsing `/openapi.json`.
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>Rinha de Backend 2023 Q3</title>
+  <link rel="stylesheet" crossorigin="anonymous" href="https://unpkg.com/swagger-ui-dist@5.17.14/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script crossorigin="anonymous" src="https://unpkg.com/swagger-ui-dist@5.17.14/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// Only lets the page run the pinned Swagger UI bundle and its own inline script, identified by
+/// its SHA-256, which must be updated along with it.
+const SWAGGER_UI_CSP: &str = "default-src 'none'; \
+    script-src https://unpkg.com/swagger-ui-dist@5.17.14/ 'sha256-VLuiJVnvDt18nCUhCe5Flxu5pIK65k9QV4rKxN9JrYI='; \
+    style-src https://unpkg.com/swagger-ui-dist@5.17.14/ 'unsafe-inline'; \
+    img-src 'self' data:; connect-src 'self'";
+
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
+
+pub async fn swagger_ui() -> impl IntoResponse {
+    (
+        [(header::CONTENT_SECURITY_POLICY, SWAGGER_UI_CSP)],
+        Html(SWAGGER_UI),
+    )
+}
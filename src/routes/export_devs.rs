@@ -0,0 +1,55 @@
+use crate::export::ExportFormat;
+use crate::migrations;
+use crate::storage::PersonStore;
+use crate::structs::{api, person};
+use axum::body::Body;
+use axum::extract::{Query, State};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use futures::stream::{self, StreamExt, TryStreamExt};
+
+#[utoipa::path(
+    get,
+    path = "/pessoas/export",
+    tag = "pessoas",
+    params(api::ExportPersonsQuery),
+    responses(
+        (status = 200, description = "Every person, as NDJSON or CSV"),
+        (status = 400, description = "Unsupported format"),
+    ),
+)]
+#[tracing::instrument(name = "Exporting developers", skip(client))]
+pub async fn export_persons(
+    State(client): State<PersonStore>,
+    Query(query): Query<api::ExportPersonsQuery>,
+) -> impl IntoResponse {
+    let format = query.format.unwrap_or_default();
+    if format == ExportFormat::Parquet {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let export_cursor = client
+        .stored_persons()
+        .find(person::not_deleted(), None)
+        .await;
+    match export_cursor {
+        Ok(cursor) => {
+            let rows = migrations::read_persons(&client, cursor)
+                .map_err(std::io::Error::other)
+                .and_then(move |dev| futures::future::ready(format.encode(dev)));
+            let body = Body::from_stream(stream::iter(format.header().map(Ok)).chain(rows));
+
+            Ok((
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, format.content_type())],
+                body,
+            ))
+        }
+        Err(error) => {
+            println!("export: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -1,6 +1,12 @@
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
 
+#[utoipa::path(
+    get,
+    path = "/health-check",
+    tag = "health",
+    responses((status = 200, description = "The server is up")),
+)]
 pub async fn health_check() -> impl IntoResponse {
     StatusCode::OK
 }
@@ -0,0 +1,74 @@
+use crate::events::PersonListeners;
+use crate::import;
+use crate::normalization::StackAliases;
+use crate::routes;
+use crate::storage::PersonStore;
+use crate::validation::BirthDateRules;
+use axum::body::Body;
+use axum::extract::State;
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use futures::stream::TryStreamExt;
+use http_body_util::Limited;
+use std::sync::Arc;
+use tokio_util::io::StreamReader;
+
+/// Larger imports get a 413; `DefaultBodyLimit` does not apply to a streamed `Body`.
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+#[utoipa::path(
+    post,
+    path = "/pessoas/bulk",
+    tag = "pessoas",
+    request_body(content = String, content_type = "application/x-ndjson", description = "One person per line"),
+    responses(
+        (status = 200, description = "Import report"),
+        (status = 400, description = "Unreadable body"),
+        (status = 413, description = "Body too large"),
+    ),
+)]
+#[tracing::instrument(
+    name = "Importing developers in bulk",
+    skip(client, stack_aliases, birth_date_rules, listeners, body)
+)]
+pub async fn import_persons(
+    State(client): State<PersonStore>,
+    State(stack_aliases): State<Arc<StackAliases>>,
+    State(birth_date_rules): State<Arc<BirthDateRules>>,
+    State(listeners): State<PersonListeners>,
+    body: Body,
+) -> impl IntoResponse {
+    let body = Body::new(Limited::new(body, MAX_BODY_BYTES));
+    let reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let import_result = import::import_persons(
+        &client,
+        &stack_aliases,
+        &birth_date_rules,
+        &listeners,
+        reader,
+        import::DEFAULT_BATCH_SIZE,
+    )
+    .await;
+
+    match import_result {
+        Ok(report) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(report),
+        )),
+        Err(error) => {
+            println!("bulk: {}", error);
+            if error
+                .get_ref()
+                .is_some_and(|inner| routes::exceeds_length_limit(inner))
+            {
+                Err(StatusCode::PAYLOAD_TOO_LARGE)
+            } else {
+                Err(StatusCode::BAD_REQUEST)
+            }
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::live_search::{LiveSearch, LiveSearchLimits};
+use crate::normalization::StackAliases;
+use crate::search_index::SearchIndex;
+use crate::storage::PersonStore;
+use crate::structs::person::Person;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::extract::State;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[utoipa::path(
+    get,
+    path = "/pessoas/live",
+    tag = "pessoas",
+    responses(
+        (status = 101, description = "WebSocket receiving `LiveSearchRequest`s and sending `LiveSearchEvent`s"),
+        (status = 503, description = "Too many live search connections"),
+    ),
+)]
+#[tracing::instrument(
+    name = "Subscribing to a live search",
+    skip(upgrade, client, stack_aliases, search_index, person_feed, limits)
+)]
+pub async fn live_search(
+    upgrade: WebSocketUpgrade,
+    State(client): State<PersonStore>,
+    State(stack_aliases): State<Arc<StackAliases>>,
+    State(search_index): State<Option<Arc<SearchIndex>>>,
+    State(person_feed): State<broadcast::Sender<Person>>,
+    State(limits): State<Arc<LiveSearchLimits>>,
+) -> Response {
+    let Some(connection) = limits.try_connect() else {
+        println!("live search: too many connections");
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let session = LiveSearch::new(client, stack_aliases, search_index, &person_feed, &limits);
+
+    upgrade.on_upgrade(move |socket| async move {
+        session.serve(socket).await;
+        drop(connection);
+    })
+}
@@ -0,0 +1,116 @@
+use crate::migrations;
+use crate::storage::PersonStore;
+use crate::structs::{api, person};
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::{
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use mongodb::bson::doc;
+use mongodb::options::FindOptions;
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use uuid::Uuid;
+
+const LAST_EVENT_ID: &str = "last-event-id";
+/// Clients missing more persons than this get a `resync` event instead of a replay.
+pub const MAX_REPLAYED_PERSONS: usize = 1000;
+const RESYNC_EVENT: &str = "resync";
+
+#[utoipa::path(
+    get,
+    path = "/pessoas/stream",
+    tag = "pessoas",
+    params(("Last-Event-ID" = Option<Uuid>, Header, description = "Replays the persons created after this one, up to 1000")),
+    responses(
+        (status = 200, description = "Server-sent events, one per created person, or first a `resync` event when too many were missed to replay", content_type = "text/event-stream", body = api::PersonBody),
+    ),
+)]
+#[tracing::instrument(name = "Streaming new developers", skip(client, person_feed, headers))]
+pub async fn stream_persons(
+    State(client): State<PersonStore>,
+    State(person_feed): State<broadcast::Sender<person::Person>>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // Subscribes before replaying so persons created meanwhile aren't missed.
+    let live_devs = BroadcastStream::new(person_feed.subscribe());
+    let last_event_id = headers
+        .get(LAST_EVENT_ID)
+        .and_then(|last_event_id| last_event_id.to_str().ok())
+        .and_then(|last_event_id| Uuid::parse_str(last_event_id).ok());
+    let mut missed_devs = match last_event_id {
+        Some(last_event_id) => match created_after(&client, last_event_id).await {
+            Ok(devs) => devs,
+            Err(error) => {
+                println!("stream: {}", error);
+                return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        },
+        None => vec![],
+    };
+    let resync = missed_devs.len() > MAX_REPLAYED_PERSONS;
+    if resync {
+        missed_devs.clear();
+    }
+
+    let replayed_ids: HashSet<Uuid> = missed_devs.iter().map(|dev| dev.id).collect();
+    let live_devs = live_devs.filter_map(move |received| {
+        futures::future::ready(match received {
+            Ok(dev) if !replayed_ids.contains(&dev.id) => Some(dev),
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                println!("stream: subscriber lagged behind {} persons", skipped);
+                None
+            }
+        })
+    });
+    let resync_event = resync.then(|| Ok(Event::default().event(RESYNC_EVENT).data("")));
+    let events =
+        stream::iter(resync_event).chain(stream::iter(missed_devs).chain(live_devs).map(|dev| {
+            Event::default()
+                .event("pessoa")
+                .id(dev.id.to_string())
+                .json_data(api::PersonBody::from(dev))
+        }));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Persons created after the one with `last_id`, oldest first, one more than
+/// [`MAX_REPLAYED_PERSONS`] at most. Nothing can be replayed when that person is unknown or
+/// predates creation timestamps.
+async fn created_after(
+    client: &PersonStore,
+    last_id: Uuid,
+) -> Result<Vec<person::Person>, mongodb::error::Error> {
+    let devs_store = client.stored_persons();
+    let last_created_at = devs_store
+        .find_one(doc! {"_id": last_id}, None)
+        .await?
+        .map(|stored| migrations::read_person(stored, client.stack_aliases()))
+        .transpose()?
+        .and_then(|dev| dev.created_at);
+    let Some(last_created_at) = last_created_at else {
+        return Ok(vec![]);
+    };
+
+    let options = FindOptions::builder()
+        .sort(doc! {"created_at": 1, "_id": 1})
+        .limit(MAX_REPLAYED_PERSONS as i64 + 1)
+        .build();
+    let cursor = devs_store
+        .find(
+            doc! {
+                "created_at": {"$gte": last_created_at},
+                "_id": {"$ne": last_id},
+                "deleted_at": null,
+            },
+            options,
+        )
+        .await?;
+    migrations::read_persons(client, cursor).try_collect().await
+}
@@ -0,0 +1,126 @@
+use crate::normalization::{escape_regex, normalize_stack};
+use crate::storage::PersonStore;
+use crate::structs::{api, person};
+use axum::extract::{Query, State};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Document};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 100;
+
+#[utoipa::path(
+    get,
+    path = "/stacks",
+    tag = "stacks",
+    params(api::StackQuery),
+    responses((status = 200, body = [api::StackCountBody])),
+)]
+#[tracing::instrument(name = "Suggesting stacks", skip(client))]
+pub async fn suggest_stacks(
+    State(client): State<PersonStore>,
+    Query(query): Query<api::StackQuery>,
+) -> impl IntoResponse {
+    count_stacks(client, query.prefix, query.limit).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/stacks/top",
+    tag = "stacks",
+    params(api::StackQuery),
+    responses((status = 200, body = [api::StackCountBody])),
+)]
+#[tracing::instrument(name = "Ranking stacks", skip(client))]
+pub async fn top_stacks(
+    State(client): State<PersonStore>,
+    Query(query): Query<api::StackQuery>,
+) -> impl IntoResponse {
+    count_stacks(client, None, query.limit).await
+}
+
+/// Aggregation stages turning every person into one `{canonical, label}` document per stack,
+/// pairing each canonical stack with the spelling it was submitted with, so counts are grouped
+/// by canonical name while still showing a human spelling.
+pub fn labeled_stacks() -> Vec<Document> {
+    vec![
+        doc! {
+            "$project": {
+                "stacks": {
+                    "$zip": {"inputs": ["$stacks", {"$ifNull": ["$stack_labels", "$stacks"]}]}
+                }
+            }
+        },
+        doc! {"$unwind": "$stacks"},
+        doc! {
+            "$project": {
+                "canonical": {"$arrayElemAt": ["$stacks", 0]},
+                "label": {"$arrayElemAt": ["$stacks", 1]},
+            }
+        },
+    ]
+}
+
+async fn count_stacks(
+    client: PersonStore,
+    prefix: Option<String>,
+    limit: Option<u32>,
+) -> impl IntoResponse {
+    let devs_store = client.persons();
+    let mut pipeline = vec![doc! {"$match": person::not_deleted()}];
+    pipeline.extend(labeled_stacks());
+    if let Some(prefix) = prefix.filter(|prefix| !prefix.is_empty()) {
+        pipeline.push(doc! {
+            "$match": {
+                "canonical": mongodb::bson::Regex {
+                    pattern: format!("^{}", escape_regex(&normalize_stack(&prefix))),
+                    options: String::from("i"),
+                }
+            }
+        });
+    }
+    pipeline.extend([
+        doc! {
+            "$group": {
+                "_id": "$canonical",
+                "label": {"$first": "$label"},
+                "count": {"$sum": 1},
+            }
+        },
+        doc! {"$sort": {"count": -1, "_id": 1}},
+        doc! {"$limit": limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)},
+        doc! {"$project": {"_id": 0, "stack": "$label", "count": 1}},
+    ]);
+
+    let stacks_cursor = devs_store.aggregate(pipeline, None).await;
+    let found_stacks = match stacks_cursor {
+        Ok(cursor) => {
+            cursor
+                .and_then(|stack| {
+                    futures::future::ready(
+                        mongodb::bson::from_document::<api::StackCountBody>(stack)
+                            .map_err(mongodb::error::Error::from),
+                    )
+                })
+                .try_collect::<Vec<api::StackCountBody>>()
+                .await
+        }
+        Err(error) => Err(error),
+    };
+
+    match found_stacks {
+        Ok(stacks) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(stacks),
+        )),
+        Err(error) => {
+            println!("stacks: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
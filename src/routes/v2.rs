@@ -0,0 +1,339 @@
+use crate::field_naming::FieldNaming;
+use crate::idempotency::{self, HashedJson, HashedJsonRejection};
+use crate::normalization::StackAliases;
+use crate::routes::devs::{CreateError, PersonCreator};
+use crate::search;
+use crate::search_index::SearchIndex;
+use crate::storage::{self, PersonStore};
+use crate::structs::api;
+use axum::extract::rejection::{PathRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::{
+    http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+/// A failed `/v2` request, answered with an [`api::ErrorBody`].
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn internal() -> Self {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "something went wrong, try again later",
+        )
+    }
+
+    fn person_not_found(id: Uuid) -> Self {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            "person_not_found",
+            format!("no person with id {}", id),
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(api::ErrorBody {
+                error: api::ErrorDetail {
+                    code: self.code.to_string(),
+                    message: self.message,
+                },
+            }),
+        )
+            .into_response()
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        ApiError::new(rejection.status(), "invalid_path", rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        ApiError::new(rejection.status(), "invalid_query", rejection.body_text())
+    }
+}
+
+impl From<HashedJsonRejection> for ApiError {
+    fn from(rejection: HashedJsonRejection) -> Self {
+        ApiError::new(rejection.status(), "invalid_body", rejection.body_text())
+    }
+}
+
+impl From<CreateError> for ApiError {
+    fn from(error: CreateError) -> Self {
+        let (code, message) = match &error {
+            CreateError::InvalidIdempotencyKey => (
+                "invalid_idempotency_key",
+                String::from("idempotency keys are 1 to 255 visible ASCII characters"),
+            ),
+            CreateError::InvalidPerson(invalid) => ("invalid_person", invalid.to_string()),
+            CreateError::IdempotencyKeyInUse => (
+                "idempotency_key_in_use",
+                String::from("a request with this idempotency key is still being handled"),
+            ),
+            CreateError::IdempotencyKeyReused => (
+                "idempotency_key_reused",
+                String::from("this idempotency key was used with another body"),
+            ),
+            CreateError::NicknameTaken => (
+                "nickname_taken",
+                String::from("the nickname is already taken"),
+            ),
+            CreateError::Internal => return ApiError::internal(),
+        };
+        ApiError::new(error.status(), code, message)
+    }
+}
+
+fn envelope<T>(data: T) -> api::Envelope<T> {
+    api::Envelope { data, meta: None }
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/pessoas/{id}",
+    tag = "v2",
+    params(("id" = Uuid, Path, description = "Person id")),
+    responses(
+        (status = 200, content(
+            ("application/json" = api::PersonEnvelope),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonEnvelope),
+        )),
+        (status = 404, body = api::ErrorBody),
+    ),
+)]
+#[tracing::instrument(name = "Looking for a developer (v2)", skip(client, id))]
+pub async fn get_person(
+    State(client): State<PersonStore>,
+    id: Result<Path<Uuid>, PathRejection>,
+    naming: FieldNaming,
+) -> Result<Response, ApiError> {
+    let Path(id) = id?;
+    match storage::find_person(&client, id).await {
+        Ok(Some(dev)) => Ok(naming.render(envelope(api::PersonBody::from(dev)))),
+        Ok(None) => Err(ApiError::person_not_found(id)),
+        Err(error) => {
+            println!("v2 get_by_id: {}", error);
+            Err(ApiError::internal())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/pessoas",
+    tag = "v2",
+    request_body = api::CreatePersonBody,
+    params(("Idempotency-Key" = Option<String>, Header, description = "Replays the first response to retries with the same key")),
+    responses(
+        (status = 201, headers(("Location" = String)), content(
+            ("application/json" = api::PersonEnvelope),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonEnvelope),
+        )),
+        (status = 400, body = api::ErrorBody),
+        (status = 409, body = api::ErrorBody),
+        (status = 422, body = api::ErrorBody),
+    ),
+)]
+#[tracing::instrument(name = "Adding a new developer (v2)", skip(creator, headers, body))]
+pub async fn create_person(
+    State(creator): State<PersonCreator>,
+    headers: HeaderMap,
+    naming: FieldNaming,
+    body: Result<HashedJson<api::CreatePersonBody>, HashedJsonRejection>,
+) -> Result<Response, ApiError> {
+    let HashedJson { value: body, hash } = body?;
+    let created = creator.create(&headers, body, &hash).await?;
+
+    let dev: api::PersonBody = serde_json::from_str(&created.response.body).map_err(|error| {
+        println!("v2 post: {}", error);
+        ApiError::internal()
+    })?;
+    let mut response = (
+        StatusCode::from_u16(created.response.status).unwrap_or(StatusCode::OK),
+        [(
+            header::LOCATION,
+            format!("/v2{}", created.response.location),
+        )],
+        naming.render(envelope(dev)),
+    )
+        .into_response();
+    if created.replayed {
+        response.headers_mut().insert(
+            HeaderName::from_static(idempotency::IDEMPOTENT_REPLAYED_HEADER),
+            HeaderValue::from_static("true"),
+        );
+    }
+    Ok(response)
+}
+
+/// Searches like `GET /pessoas`, a page at a time.
+#[utoipa::path(
+    get,
+    path = "/v2/pessoas",
+    tag = "v2",
+    params(api::SearchPersonQuery, api::PageQuery),
+    responses(
+        (status = 200, content(
+            ("application/json" = api::PersonPageEnvelope),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonPageEnvelope),
+        )),
+        (status = 400, body = api::ErrorBody),
+    ),
+)]
+#[tracing::instrument(
+    name = "Searching for a developer (v2)",
+    skip(client, stack_aliases, search_index, query, page)
+)]
+pub async fn search_persons(
+    State(client): State<PersonStore>,
+    State(stack_aliases): State<Arc<StackAliases>>,
+    State(search_index): State<Option<Arc<SearchIndex>>>,
+    query: Result<Query<api::SearchPersonQuery>, QueryRejection>,
+    page: Result<Query<api::PageQuery>, QueryRejection>,
+    naming: FieldNaming,
+) -> Result<Response, ApiError> {
+    let Query(query) = query?;
+    let Query(page) = page?;
+    let page_number = page.page.unwrap_or(1);
+    let per_page = page.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if page_number == 0 || !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            "invalid_pagination",
+            format!(
+                "page must be at least 1 and per_page between 1 and {}",
+                MAX_PER_PAGE
+            ),
+        ));
+    }
+
+    let offset = u64::from(page_number - 1).saturating_mul(u64::from(per_page));
+    let found = search::find_persons_page(
+        &client,
+        &stack_aliases,
+        search_index,
+        &query,
+        offset,
+        u64::from(per_page),
+    )
+    .await;
+    let (found_devs, total) = match found {
+        Ok(found) => found,
+        Err(search::SearchError::Invalid(error)) => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                "invalid_search",
+                error.to_string(),
+            ))
+        }
+        Err(error) => {
+            println!("v2 persons?t=QUERY: {}", error);
+            return Err(ApiError::internal());
+        }
+    };
+
+    let explain = query.explain.unwrap_or(false);
+    let persons = found_devs
+        .into_iter()
+        .map(|(dev, score)| api::ScoredPersonBody {
+            person: api::PersonBody::from(dev),
+            score: explain.then_some(score),
+        })
+        .collect::<Vec<api::ScoredPersonBody>>();
+    Ok(naming.render(api::Envelope {
+        data: persons,
+        meta: Some(api::PageMeta {
+            page: page_number,
+            per_page,
+            total,
+        }),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/pessoas/{id}",
+    tag = "v2",
+    params(("id" = Uuid, Path, description = "Person id")),
+    responses(
+        (status = 204, description = "Soft-deleted"),
+        (status = 404, body = api::ErrorBody),
+    ),
+)]
+#[tracing::instrument(name = "Deleting a developer (v2)", skip(client, id))]
+pub async fn delete_person(
+    State(client): State<PersonStore>,
+    id: Result<Path<Uuid>, PathRejection>,
+) -> Result<StatusCode, ApiError> {
+    let Path(id) = id?;
+    match storage::soft_delete_person(&client, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::person_not_found(id)),
+        Err(error) => {
+            println!("v2 delete: {}", error);
+            Err(ApiError::internal())
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/pessoas/{id}/restore",
+    tag = "v2",
+    params(("id" = Uuid, Path, description = "Person id")),
+    responses(
+        (status = 200, content(
+            ("application/json" = api::PersonEnvelope),
+            ("application/vnd.rinha.en+json" = api::EnglishPersonEnvelope),
+        )),
+        (status = 404, body = api::ErrorBody),
+    ),
+)]
+#[tracing::instrument(name = "Restoring a developer (v2)", skip(client, id))]
+pub async fn restore_person(
+    State(client): State<PersonStore>,
+    id: Result<Path<Uuid>, PathRejection>,
+    naming: FieldNaming,
+) -> Result<Response, ApiError> {
+    let Path(id) = id?;
+    match storage::restore_person(&client, id).await {
+        Ok(Some(dev)) => Ok(naming.render(envelope(api::PersonBody::from(dev)))),
+        Ok(None) => Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            "person_not_found",
+            format!("no deleted person with id {}", id),
+        )),
+        Err(error) => {
+            println!("v2 restore: {}", error);
+            Err(ApiError::internal())
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use crate::structs::api;
+use crate::structs::webhook::{Webhook, WebhookDelivery, WebhookDeliveryLog};
+use crate::webhooks::{
+    WebhookQueue, DEAD_LETTERS_COLLECTION, DELIVERIES_COLLECTION, DELIVERY_LOGS_COLLECTION,
+    WEBHOOKS_COLLECTION,
+};
+use axum::extract::{Path, State};
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::FindOptions;
+use mongodb::{Collection, Database};
+use uuid::Uuid;
+
+/// Most recent delivery attempts listed per webhook.
+const MAX_LISTED_DELIVERIES: i64 = 100;
+
+fn generate_secret() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    request_body = api::CreateWebhookBody,
+    responses(
+        (status = 201, body = api::WebhookBody, headers(("Location" = String))),
+        (status = 422, description = "Invalid URL"),
+    ),
+)]
+#[tracing::instrument(name = "Adding a webhook", skip(client, webhooks, body))]
+pub async fn create_webhook(
+    State(client): State<Database>,
+    State(webhooks): State<WebhookQueue>,
+    Json(body): Json<api::CreateWebhookBody>,
+) -> impl IntoResponse {
+    if !webhooks.accepts_url(&body.url) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    let webhook = Webhook {
+        id: Uuid::new_v4(),
+        url: body.url,
+        secret: body.secret.unwrap_or_else(generate_secret),
+        created_at: DateTime::now(),
+    };
+    let webhooks_store: Collection<Webhook> = client.collection(WEBHOOKS_COLLECTION);
+    let inserted = webhooks_store.insert_one(&webhook, None).await;
+    webhooks.invalidate();
+    match inserted {
+        Ok(_) => {
+            let secret = webhook.secret.clone();
+            Ok((
+                StatusCode::CREATED,
+                [
+                    (header::LOCATION, format!("/webhooks/{}", &webhook.id)),
+                    (header::CONTENT_TYPE, String::from("application/json")),
+                ],
+                Json(api::WebhookBody {
+                    secret: Some(secret),
+                    ..api::WebhookBody::from(webhook)
+                }),
+            ))
+        }
+        Err(error) => {
+            println!("webhooks: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "webhooks",
+    responses((status = 200, body = [api::WebhookBody])),
+)]
+#[tracing::instrument(name = "Listing webhooks", skip(client))]
+pub async fn list_webhooks(State(client): State<Database>) -> impl IntoResponse {
+    let webhooks_store: Collection<Webhook> = client.collection(WEBHOOKS_COLLECTION);
+    let options = FindOptions::builder()
+        .sort(doc! {"created_at": 1, "_id": 1})
+        .build();
+    let found_webhooks = match webhooks_store.find(None, options).await {
+        Ok(cursor) => cursor.try_collect::<Vec<Webhook>>().await,
+        Err(error) => Err(error),
+    };
+
+    match found_webhooks {
+        Ok(webhooks) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(
+                webhooks
+                    .into_iter()
+                    .map(api::WebhookBody::from)
+                    .collect::<Vec<api::WebhookBody>>(),
+            ),
+        )),
+        Err(error) => {
+            println!("webhooks: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 200, body = api::WebhookBody),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+#[tracing::instrument(name = "Looking for a webhook", skip(client))]
+pub async fn get_webhook(
+    State(client): State<Database>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    let webhooks_store: Collection<Webhook> = client.collection(WEBHOOKS_COLLECTION);
+    match webhooks_store.find_one(doc! {"_id": id}, None).await {
+        Ok(Some(webhook)) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(api::WebhookBody::from(webhook)),
+        )),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(error) => {
+            println!("webhooks/:id: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Changes the webhook URL, and its secret when one is given.
+#[utoipa::path(
+    put,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    request_body = api::CreateWebhookBody,
+    responses(
+        (status = 200, body = api::WebhookBody),
+        (status = 404, description = "No such webhook"),
+        (status = 422, description = "Invalid URL"),
+    ),
+)]
+#[tracing::instrument(name = "Updating a webhook", skip(client, webhooks, body))]
+pub async fn update_webhook(
+    State(client): State<Database>,
+    State(webhooks): State<WebhookQueue>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<api::CreateWebhookBody>,
+) -> impl IntoResponse {
+    if !webhooks.accepts_url(&body.url) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    let mut changes = doc! {"url": &body.url};
+    if let Some(secret) = &body.secret {
+        changes.insert("secret", secret);
+    }
+    let webhooks_store: Collection<Webhook> = client.collection(WEBHOOKS_COLLECTION);
+    let updated = webhooks_store
+        .find_one_and_update(
+            doc! {"_id": id},
+            doc! {"$set": changes},
+            mongodb::options::FindOneAndUpdateOptions::builder()
+                .return_document(mongodb::options::ReturnDocument::After)
+                .build(),
+        )
+        .await;
+    webhooks.invalidate();
+
+    match updated {
+        Ok(Some(webhook)) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(api::WebhookBody {
+                secret: body.secret,
+                ..api::WebhookBody::from(webhook)
+            }),
+        )),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(error) => {
+            println!("webhooks/:id: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Deletes the webhook along with its pending deliveries.
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 204, description = "Deleted along with its pending deliveries"),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+#[tracing::instrument(name = "Deleting a webhook", skip(client, webhooks))]
+pub async fn delete_webhook(
+    State(client): State<Database>,
+    State(webhooks): State<WebhookQueue>,
+    Path(id): Path<Uuid>,
+) -> StatusCode {
+    let webhooks_store: Collection<Webhook> = client.collection(WEBHOOKS_COLLECTION);
+    let deliveries_store: Collection<WebhookDelivery> = client.collection(DELIVERIES_COLLECTION);
+    let deleted = webhooks_store.delete_one(doc! {"_id": id}, None).await;
+    webhooks.invalidate();
+    match deleted {
+        Ok(result) if result.deleted_count == 0 => StatusCode::NOT_FOUND,
+        Ok(_) => match deliveries_store
+            .delete_many(doc! {"webhook_id": id}, None)
+            .await
+        {
+            Ok(_) => StatusCode::NO_CONTENT,
+            Err(error) => {
+                println!("webhooks/:id: {}", error);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        },
+        Err(error) => {
+            println!("webhooks/:id: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// The latest delivery attempts of a webhook, newest first.
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 200, body = [api::WebhookDeliveryLogBody]),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+#[tracing::instrument(name = "Listing webhook deliveries", skip(client))]
+pub async fn list_deliveries(
+    State(client): State<Database>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    ensure_webhook_exists(&client, id).await?;
+    let logs_store: Collection<WebhookDeliveryLog> = client.collection(DELIVERY_LOGS_COLLECTION);
+    let options = FindOptions::builder()
+        .sort(doc! {"attempted_at": -1, "attempt": -1})
+        .limit(MAX_LISTED_DELIVERIES)
+        .build();
+    let found_logs = match logs_store.find(doc! {"webhook_id": id}, options).await {
+        Ok(cursor) => cursor.try_collect::<Vec<WebhookDeliveryLog>>().await,
+        Err(error) => Err(error),
+    };
+
+    match found_logs {
+        Ok(logs) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(
+                logs.into_iter()
+                    .map(api::WebhookDeliveryLogBody::from)
+                    .collect::<Vec<api::WebhookDeliveryLogBody>>(),
+            ),
+        )),
+        Err(error) => {
+            println!("webhooks/:id/deliveries: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Deliveries of a webhook that were given up on after too many failed attempts.
+#[utoipa::path(
+    get,
+    path = "/webhooks/{id}/dead-letters",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    responses(
+        (status = 200, body = [api::WebhookDeadLetterBody]),
+        (status = 404, description = "No such webhook"),
+    ),
+)]
+#[tracing::instrument(name = "Listing webhook dead letters", skip(client))]
+pub async fn list_dead_letters(
+    State(client): State<Database>,
+    Path(id): Path<Uuid>,
+) -> impl IntoResponse {
+    ensure_webhook_exists(&client, id).await?;
+    let dead_letters_store: Collection<WebhookDelivery> =
+        client.collection(DEAD_LETTERS_COLLECTION);
+    let options = FindOptions::builder()
+        .sort(doc! {"created_at": -1, "_id": 1})
+        .limit(MAX_LISTED_DELIVERIES)
+        .build();
+    let found_dead_letters = match dead_letters_store
+        .find(doc! {"webhook_id": id}, options)
+        .await
+    {
+        Ok(cursor) => cursor.try_collect::<Vec<WebhookDelivery>>().await,
+        Err(error) => Err(error),
+    };
+
+    match found_dead_letters {
+        Ok(dead_letters) => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/json")],
+            Json(
+                dead_letters
+                    .into_iter()
+                    .map(api::WebhookDeadLetterBody::from)
+                    .collect::<Vec<api::WebhookDeadLetterBody>>(),
+            ),
+        )),
+        Err(error) => {
+            println!("webhooks/:id/dead-letters: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn ensure_webhook_exists(client: &Database, id: Uuid) -> Result<(), StatusCode> {
+    let webhooks_store: Collection<Webhook> = client.collection(WEBHOOKS_COLLECTION);
+    match webhooks_store.count_documents(doc! {"_id": id}, None).await {
+        Ok(0) => Err(StatusCode::NOT_FOUND),
+        Ok(_) => Ok(()),
+        Err(error) => {
+            println!("webhooks/:id: {}", error);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use futures::stream::TryStreamExt;
+use mongodb::bson::{doc, Bson, Document, Regex};
+use mongodb::options::FindOptions;
+use uuid::Uuid;
+
+use crate::age;
+use crate::migrations;
+use crate::normalization::{escape_regex, fold_text, StackAliases};
+use crate::search_index::{self, SearchIndex};
+use crate::storage::PersonStore;
+use crate::structs::{api, person};
+
+const NICKNAME_WEIGHT: f64 = 1.0;
+const NAME_WEIGHT: f64 = 0.6;
+const STACK_WEIGHT: f64 = 0.4;
+
+const EXACT_SCORE: f64 = 100.0;
+const PREFIX_SCORE: f64 = 70.0;
+const SUBSTRING_SCORE: f64 = 40.0;
+const TYPO_SCORE: f64 = 20.0;
+
+/// Terms shorter than this are too ambiguous for typo tolerance.
+const MIN_FUZZY_TERM_LENGTH: usize = 4;
+const MAX_FUZZY_TERM_LENGTH: usize = 32;
+
+/// Matches ranked by relevance in memory for a page; further matches are left out of the ranking
+/// and of the total, so broad terms do not load the whole collection.
+pub const MAX_RANKED_CANDIDATES: u64 = 10_000;
+
+/// Relevance of `dev` for an already folded search term: the best match across nickname, name and
+/// stacks, where exact beats prefix beats substring beats (when `fuzzy`) a single typo.
+pub fn score(dev: &person::Person, folded_term: &str, fuzzy: bool) -> f64 {
+    let stacks = dev.display_stacks().into_iter().flatten();
+    [(NICKNAME_WEIGHT, &dev.nickname), (NAME_WEIGHT, &dev.name)]
+        .into_iter()
+        .chain(stacks.map(|stack| (STACK_WEIGHT, stack)))
+        .map(|(weight, field)| weight * field_score(&fold_text(field), folded_term, fuzzy))
+        .fold(0.0, f64::max)
+}
+
+/// Whether a free-text search for `search_term`, without typo tolerance, would find `dev`.
+pub fn matches(dev: &person::Person, search_term: &str, stack_aliases: &StackAliases) -> bool {
+    let folded_term = fold_text(search_term);
+    let canonical_stack = stack_aliases.canonicalize(search_term);
+    dev.fold_search_terms()
+        .iter()
+        .any(|term| term.contains(&folded_term))
+        || dev
+            .stacks
+            .iter()
+            .flatten()
+            .any(|stack| *stack == canonical_stack)
+}
+
+fn field_score(folded_field: &str, folded_term: &str, fuzzy: bool) -> f64 {
+    if folded_field == folded_term {
+        EXACT_SCORE
+    } else if folded_field.starts_with(folded_term)
+        || folded_field
+            .split_whitespace()
+            .any(|word| word.starts_with(folded_term))
+    {
+        PREFIX_SCORE
+    } else if folded_field.contains(folded_term) {
+        SUBSTRING_SCORE
+    } else if fuzzy
+        && is_fuzzy_term(folded_term)
+        && substring_distance(folded_term, folded_field) <= 1
+    {
+        TYPO_SCORE
+    } else {
+        0.0
+    }
+}
+
+fn score_all(
+    devs: Vec<person::Person>,
+    folded_term: &str,
+    fuzzy: bool,
+) -> Vec<(person::Person, f64)> {
+    devs.into_iter()
+        .map(|dev| {
+            let dev_score = score(&dev, folded_term, fuzzy);
+            (dev, dev_score)
+        })
+        .collect()
+}
+
+/// Sorts `devs` by descending score, breaking ties by nickname so results are stable.
+pub fn rank(
+    devs: Vec<person::Person>,
+    folded_term: &str,
+    fuzzy: bool,
+) -> Vec<(person::Person, f64)> {
+    let mut scored = score_all(devs, folded_term, fuzzy);
+    scored.sort_by(|(dev, dev_score), (other, other_score)| {
+        other_score
+            .partial_cmp(dev_score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| dev.nickname.cmp(&other.nickname))
+    });
+    scored
+}
+
+fn is_fuzzy_term(folded_term: &str) -> bool {
+    (MIN_FUZZY_TERM_LENGTH..=MAX_FUZZY_TERM_LENGTH).contains(&folded_term.chars().count())
+}
+
+/// A regex matching any text containing `folded_term` within one edit (substitution, insertion,
+/// deletion or transposition of adjacent characters), or `None` when the term is too short or too
+/// long for typo tolerance.
+pub fn fuzzy_pattern(folded_term: &str) -> Option<String> {
+    if !is_fuzzy_term(folded_term) {
+        return None;
+    }
+    let characters: Vec<String> = folded_term
+        .chars()
+        .map(|character| escape_regex(&character.to_string()))
+        .collect();
+    let join = |parts: &[String]| parts.concat();
+    let length = characters.len();
+
+    let mut variants = vec![];
+    for index in 0..=length {
+        let (before, after) = characters.split_at(index);
+        variants.push(format!("{}.{}", join(before), join(after)));
+        if index < length {
+            variants.push(format!("{}.{}", join(before), join(&after[1..])));
+            variants.push(format!("{}{}", join(before), join(&after[1..])));
+        }
+        if index + 1 < length {
+            variants.push(format!(
+                "{}{}{}{}",
+                join(before),
+                after[1],
+                after[0],
+                join(&after[2..])
+            ));
+        }
+    }
+    Some(variants.join("|"))
+}
+
+/// The fewest edits (optimal string alignment) needed to turn `pattern` into some substring of
+/// `text`.
+fn substring_distance(pattern: &str, text: &str) -> usize {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    // rows[i][j]: distance between pattern[..i] and the best substring of text ending at j.
+    let mut rows = vec![vec![0; text.len() + 1]; pattern.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for i in 1..=pattern.len() {
+        for j in 1..=text.len() {
+            let substitution = usize::from(pattern[i - 1] != text[j - 1]);
+            let mut distance = (rows[i - 1][j - 1] + substitution)
+                .min(rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1);
+            if i > 1 && j > 1 && pattern[i - 1] == text[j - 2] && pattern[i - 2] == text[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[pattern.len()].iter().copied().min().unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub enum InvalidSearch {
+    MissingCriteria,
+    EmptyBirthDateRange,
+    EmptyAgeRange,
+}
+
+impl std::fmt::Display for InvalidSearch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidSearch::MissingCriteria => write!(f, "at least one search criteria is required"),
+            InvalidSearch::EmptyBirthDateRange => {
+                write!(f, "born_after must be before born_before")
+            }
+            InvalidSearch::EmptyAgeRange => write!(f, "min_age must not exceed max_age"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SearchError {
+    Invalid(InvalidSearch),
+    Index(tantivy::TantivyError),
+    Storage(mongodb::error::Error),
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::Invalid(error) => write!(f, "{}", error),
+            SearchError::Index(error) => write!(f, "{}", error),
+            SearchError::Storage(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// Runs a person search, matching the free-text term through the embedded search index when it
+/// is enabled, and returns the persons found with their scores.
+pub async fn find_persons(
+    client: &PersonStore,
+    stack_aliases: &StackAliases,
+    search_index: Option<Arc<SearchIndex>>,
+    query: &api::SearchPersonQuery,
+) -> Result<Vec<(person::Person, f64)>, SearchError> {
+    let search_filter = search_filter(stack_aliases, search_index, query).await?;
+    let options = FindOptions::builder()
+        .sort(search_filter.sort.clone())
+        .build();
+    find_scored(client, &search_filter, options).await
+}
+
+/// Like [`find_persons`], returning only `limit` persons after the first `offset`, along with
+/// how many were found in all. Paginates in storage unless ranking by relevance, which is done
+/// here over the first [`MAX_RANKED_CANDIDATES`] matches.
+pub async fn find_persons_page(
+    client: &PersonStore,
+    stack_aliases: &StackAliases,
+    search_index: Option<Arc<SearchIndex>>,
+    query: &api::SearchPersonQuery,
+    offset: u64,
+    limit: u64,
+) -> Result<(Vec<(person::Person, f64)>, u64), SearchError> {
+    let search_filter = search_filter(stack_aliases, search_index, query).await?;
+    if search_filter.ranks_by_relevance() {
+        let options = FindOptions::builder()
+            .sort(doc! {"_id": 1})
+            .limit(MAX_RANKED_CANDIDATES as i64)
+            .build();
+        let found_devs = find_scored(client, &search_filter, options).await?;
+        let total = found_devs.len() as u64;
+        let page = found_devs
+            .into_iter()
+            .skip(usize::try_from(offset).unwrap_or(usize::MAX))
+            .take(usize::try_from(limit).unwrap_or(usize::MAX))
+            .collect();
+        return Ok((page, total));
+    }
+
+    let total = client
+        .stored_persons()
+        .count_documents(search_filter.filter.clone(), None)
+        .await
+        .map_err(SearchError::Storage)?;
+    // Pages only line up with a total order.
+    let options = FindOptions::builder()
+        .sort(search_filter.sort.clone().unwrap_or(doc! {"_id": 1}))
+        .skip(offset)
+        .limit(i64::try_from(limit).unwrap_or(i64::MAX))
+        .build();
+    let page = find_scored(client, &search_filter, options).await?;
+    Ok((page, total))
+}
+
+async fn search_filter(
+    stack_aliases: &StackAliases,
+    search_index: Option<Arc<SearchIndex>>,
+    query: &api::SearchPersonQuery,
+) -> Result<SearchFilter, SearchError> {
+    // Typo tolerance is only implemented by the storage search.
+    let indexed_ids = match (search_index, &query.search_term) {
+        (Some(search_index), Some(search_term)) if !query.fuzzy.unwrap_or(false) => Some(
+            search_index::search_persons(search_index, search_term.clone())
+                .await
+                .map_err(SearchError::Index)?,
+        ),
+        _ => None,
+    };
+    SearchFilter::from_query(query, stack_aliases, indexed_ids).map_err(SearchError::Invalid)
+}
+
+async fn find_scored(
+    client: &PersonStore,
+    search_filter: &SearchFilter,
+    options: impl Into<Option<FindOptions>>,
+) -> Result<Vec<(person::Person, f64)>, SearchError> {
+    let cursor = client
+        .stored_persons()
+        .find(search_filter.filter.clone(), options)
+        .await
+        .map_err(SearchError::Storage)?;
+    let found_devs = migrations::read_persons(client, cursor)
+        .try_collect()
+        .await
+        .map_err(SearchError::Storage)?;
+    Ok(search_filter.score(found_devs))
+}
+
+/// A person search translated into a single MongoDB filter, composing the free-text term with
+/// the structured criteria.
+#[derive(Debug)]
+pub struct SearchFilter {
+    pub filter: Document,
+    pub sort: Option<Document>,
+    folded_term: Option<String>,
+    relevance_sort: bool,
+    fuzzy: bool,
+}
+
+impl SearchFilter {
+    /// `indexed_ids`, when given, are the persons matching the free-text term according to the
+    /// embedded search index, and replace the storage text matching but for stack aliases.
+    pub fn from_query(
+        query: &api::SearchPersonQuery,
+        stack_aliases: &StackAliases,
+        indexed_ids: Option<Vec<Uuid>>,
+    ) -> Result<Self, InvalidSearch> {
+        let has_criteria = query.search_term.is_some()
+            || query.stack.is_some()
+            || query.nickname.is_some()
+            || query.born_after.is_some()
+            || query.born_before.is_some()
+            || query.min_age.is_some()
+            || query.max_age.is_some()
+            || query.location.is_some()
+            || query.seniority.is_some()
+            || query.min_level.is_some()
+            || query.min_years.is_some();
+        if !has_criteria {
+            return Err(InvalidSearch::MissingCriteria);
+        }
+        if let (Some(born_after), Some(born_before)) = (query.born_after, query.born_before) {
+            if born_after >= born_before {
+                return Err(InvalidSearch::EmptyBirthDateRange);
+            }
+        }
+        if let (Some(min_age), Some(max_age)) = (query.min_age, query.max_age) {
+            if min_age > max_age {
+                return Err(InvalidSearch::EmptyAgeRange);
+            }
+        }
+
+        let fuzzy = query.fuzzy.unwrap_or(false);
+        let mut criteria = vec![person::not_deleted()];
+        match (&query.search_term, indexed_ids) {
+            // The index only holds folded terms, so stack aliases are still resolved here. Its
+            // hits only contain every gram of the term, so the whole term is checked again.
+            (Some(search_term), Some(indexed_ids)) => criteria.push(doc! {"$or": [
+                {
+                    "_id": {"$in": indexed_ids.into_iter().map(Bson::from).collect::<Vec<Bson>>()},
+                    "search_terms": Regex {
+                        pattern: escape_regex(&fold_text(search_term)),
+                        options: String::new(),
+                    },
+                },
+                {"stacks": stack_aliases.canonicalize(search_term)},
+            ]}),
+            (Some(search_term), None) => {
+                criteria.push(doc! {"$or": term_clauses(search_term, stack_aliases, fuzzy)})
+            }
+            (None, _) => {}
+        }
+        if let Some(stack) = &query.stack {
+            criteria.push(doc! {"stacks": stack_aliases.canonicalize(stack)});
+        }
+        if query.min_level.is_some() || query.min_years.is_some() {
+            criteria
+                .push(doc! {"stack_entries": {"$elemMatch": entry_criteria(query, stack_aliases)}});
+        }
+        if let Some(nickname) = &query.nickname {
+            criteria.push(doc! {
+                "nickname": Regex {
+                    pattern: format!("^{}$", escape_regex(nickname)),
+                    options: String::from("i"),
+                }
+            });
+        }
+        // Birth dates are stored as ISO 8601 strings, which sort like the dates themselves.
+        if let Some(born_after) = query.born_after {
+            criteria.push(doc! {"birth_date": {"$gt": born_after.to_string()}});
+        }
+        if let Some(born_before) = query.born_before {
+            criteria.push(doc! {"birth_date": {"$lt": born_before.to_string()}});
+        }
+        let today = age::today();
+        if let Some(min_age) = query.min_age {
+            let latest = age::latest_birth_date(today, min_age);
+            criteria.push(doc! {"birth_date": {"$lte": latest.to_string()}});
+        }
+        if let Some(max_age) = query.max_age {
+            let too_old = age::latest_birth_date(today, max_age.saturating_add(1));
+            criteria.push(doc! {"birth_date": {"$gt": too_old.to_string()}});
+        }
+        if let Some(location) = &query.location {
+            criteria.push(doc! {
+                "location": Regex {
+                    pattern: escape_regex(location),
+                    options: String::from("i"),
+                }
+            });
+        }
+        if let Some(seniority) = query.seniority {
+            let seniority =
+                mongodb::bson::to_bson(&seniority).expect("seniorities always serialize");
+            criteria.push(doc! {"seniority": seniority});
+        }
+
+        let sort_order = query.sort.unwrap_or_default();
+        let sort = match sort_order {
+            api::SearchSort::Relevance => None,
+            api::SearchSort::NicknameAscending => Some(doc! {"nickname": 1, "_id": 1}),
+            api::SearchSort::NicknameDescending => Some(doc! {"nickname": -1, "_id": 1}),
+            api::SearchSort::NameAscending => Some(doc! {"name": 1, "_id": 1}),
+            api::SearchSort::NameDescending => Some(doc! {"name": -1, "_id": 1}),
+            api::SearchSort::BirthDateAscending => Some(doc! {"birth_date": 1, "_id": 1}),
+            api::SearchSort::BirthDateDescending => Some(doc! {"birth_date": -1, "_id": 1}),
+        };
+
+        Ok(SearchFilter {
+            filter: doc! {"$and": criteria},
+            sort,
+            folded_term: query.search_term.as_deref().map(fold_text),
+            relevance_sort: sort_order == api::SearchSort::Relevance,
+            fuzzy,
+        })
+    }
+
+    /// Whether the persons found are ordered by [`SearchFilter::score`] rather than in storage.
+    pub fn ranks_by_relevance(&self) -> bool {
+        self.relevance_sort && self.folded_term.is_some()
+    }
+
+    /// Scores the persons found with this filter, ranking them by relevance unless another sort
+    /// order was requested.
+    pub fn score(&self, devs: Vec<person::Person>) -> Vec<(person::Person, f64)> {
+        match &self.folded_term {
+            Some(folded_term) if self.relevance_sort => rank(devs, folded_term, self.fuzzy),
+            Some(folded_term) => score_all(devs, folded_term, self.fuzzy),
+            None => devs.into_iter().map(|dev| (dev, 0.0)).collect(),
+        }
+    }
+}
+
+/// Matches a stack entry meeting `min_level` and `min_years`, of the queried stack if any.
+fn entry_criteria(query: &api::SearchPersonQuery, stack_aliases: &StackAliases) -> Document {
+    let mut criteria = Document::new();
+    if let Some(stack) = &query.stack {
+        criteria.insert("stack", stack_aliases.canonicalize(stack));
+    }
+    if let Some(min_level) = query.min_level {
+        let levels = api::StackLevel::ALL
+            .into_iter()
+            .filter(|level| *level >= min_level)
+            .map(|level| mongodb::bson::to_bson(&level).expect("stack levels always serialize"))
+            .collect::<Vec<Bson>>();
+        criteria.insert("level", doc! {"$in": levels});
+    }
+    if let Some(min_years) = query.min_years {
+        criteria.insert("years", doc! {"$gte": min_years});
+    }
+    criteria
+}
+
+fn term_clauses(search_term: &str, stack_aliases: &StackAliases, fuzzy: bool) -> Vec<Document> {
+    let folded_term = fold_text(search_term);
+    let escaped_term = escape_regex(search_term);
+    let mut clauses = vec![
+        doc! {
+            "search_terms": Regex {
+                pattern: escape_regex(&folded_term),
+                options: String::new(),
+            }
+        },
+        doc! {
+            "name": Regex {
+                pattern: escaped_term.clone(),
+                options: String::from("i"),
+            }
+        },
+        doc! {
+            "stacks": {
+                "$in": [
+                    Regex {
+                        pattern: escaped_term.clone(),
+                        options: String::from("i"),
+                    }
+                ]
+            }
+        },
+        doc! {
+            "stacks": stack_aliases.canonicalize(search_term)
+        },
+        doc! {
+            "nickname": Regex {
+                pattern: escaped_term.clone(),
+                options: String::from("i"),
+            }
+        },
+    ];
+    if let Some(pattern) = fuzzy_pattern(&folded_term).filter(|_| fuzzy) {
+        clauses.push(doc! {
+            "search_terms": Regex {
+                pattern,
+                options: String::new(),
+            }
+        });
+    }
+    clauses
+}
@@ -0,0 +1,248 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::stream::TryStreamExt;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value, STORED, STRING,
+};
+use tantivy::tokenizer::NgramTokenizer;
+use tantivy::{Index, IndexReader, IndexWriter, TantivyDocument, Term};
+use uuid::Uuid;
+
+use crate::migrations;
+use crate::normalization::fold_text;
+use crate::storage::PersonStore;
+use crate::structs::person;
+
+const NGRAM_TOKENIZER: &str = "ngram";
+/// Longest gram indexed; longer terms are looked up as every gram of that size they contain.
+const NGRAM_SIZE: usize = 3;
+const WRITER_MEMORY_BUDGET: usize = 50_000_000;
+/// Hits are collected this many at a time, so every match is returned however many there are.
+const RESULTS_PAGE_SIZE: usize = 10_000;
+const REBUILD_CHUNK_SIZE: usize = 1000;
+
+/// An on-disk Tantivy index of the folded search terms of every person, giving substring search
+/// without scanning the `devs` collection. Only ids are stored; persons are hydrated from storage.
+/// Changes are only searchable once committed, see [`SearchIndex::run_committer`].
+pub struct SearchIndex {
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    /// Whether there are changes not committed yet.
+    pending: AtomicBool,
+    id_field: Field,
+    terms_field: Field,
+}
+
+impl SearchIndex {
+    pub fn open(path: &Path) -> tantivy::Result<Self> {
+        std::fs::create_dir_all(path)?;
+        let mut schema_builder = Schema::builder();
+        let id_field = schema_builder.add_text_field("id", STRING | STORED);
+        let terms_field = schema_builder.add_text_field(
+            "search_terms",
+            TextOptions::default().set_indexing_options(
+                TextFieldIndexing::default()
+                    .set_tokenizer(NGRAM_TOKENIZER)
+                    .set_index_option(IndexRecordOption::WithFreqs),
+            ),
+        );
+        let index = Index::open_or_create(MmapDirectory::open(path)?, schema_builder.build())?;
+        index
+            .tokenizers()
+            .register(NGRAM_TOKENIZER, NgramTokenizer::new(1, NGRAM_SIZE, false)?);
+
+        Ok(SearchIndex {
+            reader: index.reader()?,
+            writer: Mutex::new(index.writer(WRITER_MEMORY_BUDGET)?),
+            pending: AtomicBool::new(false),
+            id_field,
+            terms_field,
+        })
+    }
+
+    /// Indexes `devs`, replacing any previous entry with the same id, on the next commit.
+    pub fn add(&self, devs: &[person::Person]) -> tantivy::Result<()> {
+        let writer = self.lock_writer();
+        for dev in devs {
+            writer.delete_term(Term::from_field_text(self.id_field, &dev.id.to_string()));
+            writer.add_document(self.document(dev))?;
+        }
+        self.pending.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Drops the entries of the given persons on the next commit.
+    pub fn remove(&self, ids: &[Uuid]) -> tantivy::Result<()> {
+        let writer = self.lock_writer();
+        for id in ids {
+            writer.delete_term(Term::from_field_text(self.id_field, &id.to_string()));
+        }
+        self.pending.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Makes the changes made so far searchable, if there are any.
+    pub fn commit(&self) -> tantivy::Result<()> {
+        if !self.pending.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.lock_writer().commit()?;
+        self.reader.reload()
+    }
+
+    /// Commits every `interval` at most, so that a burst of writes shares a single commit instead
+    /// of syncing the index to disk once per person.
+    pub async fn run_committer(self: Arc<Self>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let search_index = self.clone();
+            let committed = tokio::task::spawn_blocking(move || search_index.commit()).await;
+            match committed {
+                Ok(Ok(())) => {}
+                Ok(Err(error)) => println!("search index: {}", error),
+                Err(error) => println!("search index: {}", error),
+            }
+        }
+    }
+
+    /// Drops every entry and indexes the whole `devs` collection again.
+    pub async fn rebuild(
+        &self,
+        client: &PersonStore,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let cursor = client
+            .stored_persons()
+            .find(person::not_deleted(), None)
+            .await?;
+        let mut chunks =
+            std::pin::pin!(migrations::read_persons(client, cursor).try_chunks(REBUILD_CHUNK_SIZE));
+        self.lock_writer().delete_all_documents()?;
+
+        let mut indexed = 0;
+        while let Some(chunk) = chunks.try_next().await.map_err(|error| error.1)? {
+            let writer = self.lock_writer();
+            for dev in &chunk {
+                writer.add_document(self.document(dev))?;
+            }
+            indexed += chunk.len();
+        }
+        self.pending.store(true, Ordering::Release);
+        self.commit()?;
+        Ok(indexed)
+    }
+
+    fn lock_writer(&self) -> std::sync::MutexGuard<'_, IndexWriter> {
+        self.writer.lock().expect("search index writer poisoned")
+    }
+
+    /// Ids of the persons whose nickname, name or stacks contain `search_term`, ignoring case and
+    /// accents, best matches first. Every gram of the term is only required in some term, so the
+    /// ids may include persons only matching across fields; callers re-check the whole term.
+    pub fn search(&self, search_term: &str) -> tantivy::Result<Vec<Uuid>> {
+        let folded_term = fold_text(search_term);
+        let characters: Vec<char> = folded_term.chars().collect();
+        if characters.is_empty() {
+            return Ok(vec![]);
+        }
+        let grams: Vec<String> = if characters.len() <= NGRAM_SIZE {
+            vec![folded_term]
+        } else {
+            characters
+                .windows(NGRAM_SIZE)
+                .map(|gram| gram.iter().collect())
+                .collect()
+        };
+        let query = BooleanQuery::new(
+            grams
+                .iter()
+                .map(|gram| {
+                    let term_query: Box<dyn Query> = Box::new(TermQuery::new(
+                        Term::from_field_text(self.terms_field, gram),
+                        IndexRecordOption::WithFreqs,
+                    ));
+                    (Occur::Must, term_query)
+                })
+                .collect(),
+        );
+
+        let searcher = self.reader.searcher();
+        let mut ids = vec![];
+        let mut offset = 0;
+        loop {
+            let top_docs = searcher.search(
+                &query,
+                &TopDocs::with_limit(RESULTS_PAGE_SIZE)
+                    .and_offset(offset)
+                    .order_by_score(),
+            )?;
+            offset += top_docs.len();
+            let last_page = top_docs.len() < RESULTS_PAGE_SIZE;
+            for (_, address) in top_docs {
+                let document: TantivyDocument = searcher.doc(address)?;
+                if let Some(id) = document
+                    .get_first(self.id_field)
+                    .and_then(|id| id.as_str())
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                {
+                    ids.push(id);
+                }
+            }
+            if last_page {
+                return Ok(ids);
+            }
+        }
+    }
+
+    fn document(&self, dev: &person::Person) -> TantivyDocument {
+        let mut document = TantivyDocument::default();
+        document.add_text(self.id_field, dev.id.to_string());
+        for term in dev.fold_search_terms() {
+            document.add_text(self.terms_field, term);
+        }
+        document
+    }
+}
+
+/// Indexes `devs` off the async runtime when the index is enabled. Failures are only logged:
+/// storage stays the source of truth and the `reindex` command recovers a stale index.
+pub async fn index_persons(search_index: Option<Arc<SearchIndex>>, devs: Vec<person::Person>) {
+    let Some(search_index) = search_index else {
+        return;
+    };
+    let indexed = tokio::task::spawn_blocking(move || search_index.add(&devs)).await;
+    match indexed {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => println!("search index: {}", error),
+        Err(error) => println!("search index: {}", error),
+    }
+}
+
+/// Unindexes `ids` off the async runtime when the index is enabled, only logging failures like
+/// [`index_persons`].
+pub async fn unindex_persons(search_index: Option<Arc<SearchIndex>>, ids: Vec<Uuid>) {
+    let Some(search_index) = search_index else {
+        return;
+    };
+    let removed = tokio::task::spawn_blocking(move || search_index.remove(&ids)).await;
+    match removed {
+        Ok(Ok(())) => {}
+        Ok(Err(error)) => println!("search index: {}", error),
+        Err(error) => println!("search index: {}", error),
+    }
+}
+
+/// Runs [`SearchIndex::search`] off the async runtime.
+pub async fn search_persons(
+    search_index: Arc<SearchIndex>,
+    search_term: String,
+) -> tantivy::Result<Vec<Uuid>> {
+    tokio::task::spawn_blocking(move || search_index.search(&search_term))
+        .await
+        .map_err(|error| tantivy::TantivyError::InternalError(error.to_string()))?
+}
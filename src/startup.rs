@@ -1,10 +1,17 @@
+use std::collections::HashMap;
 use std::io::Error;
 use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
 
-use axum::routing::{get, post};
+use axum::extract::FromRef;
+use axum::handler::Handler;
+use axum::http::Method;
+use axum::routing::{get, on, MethodFilter, MethodRouter};
 use axum::{http, Router};
 use mongodb::options::ClientOptions;
 use mongodb::{Client, Database};
+use tokio::sync::{broadcast, Notify};
 use tower::ServiceBuilder;
 use tower_http::request_id::{MakeRequestId, RequestId};
 use tower_http::{
@@ -13,26 +20,259 @@ use tower_http::{
 };
 use uuid::Uuid;
 
-use crate::configuration::{DatabaseConfiguration, StaticConfiguration};
+use crate::admin;
+use crate::avatars::Avatars;
+use crate::blob_store;
+use crate::configuration::{BlobStoreConfiguration, DatabaseConfiguration, StaticConfiguration};
+use crate::events::{PersonListeners, PERSON_FEED_CAPACITY};
+use crate::idempotency::IdempotencyStore;
+use crate::live_search::LiveSearchLimits;
+use crate::normalization::StackAliases;
+use crate::outbox::{self, OutboxRelay};
+use crate::purge::PurgeJob;
 use crate::routes;
+use crate::routes::devs::PersonCreator;
+use crate::search_index::SearchIndex;
+use crate::storage::PersonStore;
+use crate::structs::person;
+use crate::tenancy::{self, TenantRouter};
+use crate::validation::BirthDateRules;
+use crate::webhooks::{WebhookQueue, WebhookWorker};
+
+/// Everything the routes of one tenant share.
+#[derive(Clone)]
+pub struct AppState {
+    pub persons: PersonStore,
+    pub stack_aliases: Arc<StackAliases>,
+    pub search_index: Option<Arc<SearchIndex>>,
+    pub person_feed: broadcast::Sender<person::Person>,
+    pub live_search: Arc<LiveSearchLimits>,
+    pub webhooks: WebhookQueue,
+    pub outbox: Arc<Notify>,
+    pub idempotency: Arc<IdempotencyStore>,
+    pub avatars: Arc<Avatars>,
+    pub birth_date_rules: Arc<BirthDateRules>,
+}
+
+impl FromRef<AppState> for PersonStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.persons.clone()
+    }
+}
+
+impl FromRef<AppState> for Database {
+    fn from_ref(state: &AppState) -> Self {
+        state.persons.database().clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<StackAliases> {
+    fn from_ref(state: &AppState) -> Self {
+        state.stack_aliases.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<Arc<SearchIndex>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.search_index.clone()
+    }
+}
+
+impl FromRef<AppState> for broadcast::Sender<person::Person> {
+    fn from_ref(state: &AppState) -> Self {
+        state.person_feed.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<LiveSearchLimits> {
+    fn from_ref(state: &AppState) -> Self {
+        state.live_search.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<IdempotencyStore> {
+    fn from_ref(state: &AppState) -> Self {
+        state.idempotency.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Avatars> {
+    fn from_ref(state: &AppState) -> Self {
+        state.avatars.clone()
+    }
+}
+
+impl FromRef<AppState> for PersonListeners {
+    fn from_ref(state: &AppState) -> Self {
+        PersonListeners {
+            search_index: state.search_index.clone(),
+            feed: Some(state.person_feed.clone()),
+            outbox: Some(state.outbox.clone()),
+        }
+    }
+}
+
+impl FromRef<AppState> for WebhookQueue {
+    fn from_ref(state: &AppState) -> Self {
+        state.webhooks.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<BirthDateRules> {
+    fn from_ref(state: &AppState) -> Self {
+        state.birth_date_rules.clone()
+    }
+}
+
+impl FromRef<AppState> for PersonCreator {
+    fn from_ref(state: &AppState) -> Self {
+        PersonCreator {
+            client: state.persons.clone(),
+            stack_aliases: state.stack_aliases.clone(),
+            birth_date_rules: state.birth_date_rules.clone(),
+            listeners: PersonListeners::from_ref(state),
+            idempotency: state.idempotency.clone(),
+        }
+    }
+}
 
 pub struct Application {
     app: Router,
     listener: tokio::net::TcpListener,
+    tenants: Vec<TenantJobs>,
+}
+
+/// The background jobs working on the database of one tenant.
+struct TenantJobs {
+    persons: PersonStore,
+    idempotency: Arc<IdempotencyStore>,
+    webhook_worker: WebhookWorker,
+    outbox_relay: OutboxRelay,
+    purge_job: PurgeJob,
+    /// With how often it is committed.
+    search_index: Option<(Arc<SearchIndex>, Duration)>,
+}
+
+impl TenantJobs {
+    fn spawn(self) {
+        if let Some((search_index, commit_interval)) = self.search_index {
+            tokio::spawn(search_index.run_committer(commit_interval));
+        }
+        tokio::spawn(self.webhook_worker.run());
+        tokio::spawn(self.outbox_relay.run());
+        tokio::spawn(self.purge_job.run());
+        tokio::spawn(async move {
+            match admin::migrate(&self.persons, &self.idempotency).await {
+                Ok(ran) => {
+                    for name in ran {
+                        tracing::info!(migration = name, "Applied a migration");
+                    }
+                }
+                Err(error) => println!("migrate: {}", error),
+            }
+        });
+    }
+}
+
+/// The state and jobs of a tenant, with a connection pool of its own. Files are kept apart under
+/// a directory named after `tenant_id`, when there are tenants.
+async fn build_tenant(
+    static_config: &StaticConfiguration,
+    database_config: &DatabaseConfiguration,
+    tenant_id: Option<&str>,
+    live_search: Arc<LiveSearchLimits>,
+) -> (AppState, TenantJobs) {
+    let mongodb_pool = get_database_connection(database_config.clone())
+        .await
+        .expect("failed to connect to mongodb");
+    let stack_aliases = Arc::new(StackAliases::new(&static_config.stack_aliases));
+    let persons = PersonStore::new(
+        mongodb_pool.clone(),
+        &database_config.collection_name,
+        stack_aliases.clone(),
+    );
+    let search_index = static_config
+        .search_index
+        .as_ref()
+        .map(|search_index_config| {
+            Arc::new(
+                SearchIndex::open(&tenancy::tenant_path(&search_index_config.path, tenant_id))
+                    .expect("failed to open search index"),
+            )
+        });
+    let committed_search_index = static_config
+        .search_index
+        .as_ref()
+        .zip(search_index.clone())
+        .map(|(search_index_config, search_index)| {
+            (
+                search_index,
+                Duration::from_millis(search_index_config.commit_interval_ms),
+            )
+        });
+    let webhooks = WebhookQueue::new(mongodb_pool.clone(), &static_config.webhooks);
+    let webhook_worker = WebhookWorker::new(webhooks.clone(), &static_config.webhooks);
+    let outbox_relay = OutboxRelay::new(
+        persons.clone(),
+        vec![
+            Arc::new(webhooks.clone()),
+            outbox::sink_from_configuration(&static_config.outbox.sink),
+        ],
+        &static_config.outbox,
+    );
+    let idempotency = Arc::new(IdempotencyStore::new(
+        mongodb_pool.clone(),
+        &static_config.idempotency,
+    ));
+    let blob_store_config = match &static_config.avatars.store {
+        BlobStoreConfiguration::Filesystem { path } => BlobStoreConfiguration::Filesystem {
+            path: tenancy::tenant_path(path, tenant_id),
+        },
+        BlobStoreConfiguration::Gridfs { bucket } => BlobStoreConfiguration::Gridfs {
+            bucket: bucket.clone(),
+        },
+    };
+    let avatars = Arc::new(Avatars::new(
+        blob_store::blob_store_from_configuration(&blob_store_config, &mongodb_pool),
+        &static_config.avatars,
+    ));
+    let purge_job = PurgeJob::new(
+        persons.clone(),
+        search_index.clone(),
+        avatars.clone(),
+        &static_config.soft_delete,
+    );
+    let app_state = AppState {
+        persons: persons.clone(),
+        stack_aliases,
+        search_index,
+        person_feed: broadcast::channel(PERSON_FEED_CAPACITY).0,
+        live_search,
+        webhooks,
+        outbox: outbox_relay.waker(),
+        idempotency: idempotency.clone(),
+        avatars,
+        birth_date_rules: Arc::new(BirthDateRules::new(&static_config.birth_date)),
+    };
+    let jobs = TenantJobs {
+        persons,
+        idempotency,
+        webhook_worker,
+        outbox_relay,
+        purge_job,
+        search_index: committed_search_index,
+    };
+    (app_state, jobs)
 }
 
 impl Application {
-    pub async fn build(static_config: StaticConfiguration) -> Self {
+    pub async fn build(mut static_config: StaticConfiguration) -> Self {
         let server_address =
             SocketAddr::from((Ipv4Addr::UNSPECIFIED, static_config.application_port));
         let server_listener = tokio::net::TcpListener::bind(server_address)
             .await
             .expect("failed to bind random port");
 
-        let mongodb_pool = get_database_connection(static_config.database)
-            .await
-            .expect("failed to connect to mongodb");
-
         let sensitive_headers: std::sync::Arc<[_]> =
             vec![http::header::AUTHORIZATION, http::header::COOKIE].into();
         let tracing_middleware = ServiceBuilder::new()
@@ -50,22 +290,55 @@ impl Application {
             .propagate_x_request_id()
             .sensitive_response_headers(sensitive_headers);
 
-        let app = Router::new()
-            .route("/pessoas/:id", get(routes::devs::get_person))
-            .route("/pessoas", post(routes::devs::create_person))
-            .route("/pessoas", get(routes::devs::search_persons))
-            .route("/contagem-pessoas", get(routes::count_devs::count_persons))
-            .layer(tracing_middleware)
-            .route("/health-check", get(routes::health_check::health_check))
-            .with_state(mongodb_pool);
+        if let Some(tenancy) = &mut static_config.tenancy {
+            tenancy.validate().expect("invalid tenancy configuration");
+        }
+        let live_search = Arc::new(LiveSearchLimits::new(&static_config.live_search));
+        let (app, tenants) = match &static_config.tenancy {
+            None => {
+                let (app_state, jobs) =
+                    build_tenant(&static_config, &static_config.database, None, live_search).await;
+                let app = api_routes().layer(tracing_middleware).with_state(app_state);
+                (app, vec![jobs])
+            }
+            Some(tenancy) => {
+                let mut routers = HashMap::new();
+                let mut tenants = vec![];
+                for tenant in &tenancy.tenants {
+                    let (app_state, jobs) = build_tenant(
+                        &static_config,
+                        &static_config.database.for_tenant(tenant),
+                        Some(&tenant.id),
+                        live_search.clone(),
+                    )
+                    .await;
+                    routers.insert(tenant.id.clone(), api_routes().with_state(app_state));
+                    tenants.push(jobs);
+                }
+                let tenant_router = TenantRouter::new(tenancy.resolve_from.clone(), routers);
+                let app = Router::new()
+                    .fallback(tenancy::route_to_tenant)
+                    .layer(tracing_middleware)
+                    .with_state(Arc::new(tenant_router));
+                (app, tenants)
+            }
+        };
+        let app = app
+            .route(HEALTH_CHECK_PATH, get(routes::health_check::health_check))
+            .route("/openapi.json", get(routes::docs::openapi_json))
+            .route("/docs", get(routes::docs::swagger_ui));
 
         Application {
             app,
             listener: server_listener,
+            tenants,
         }
     }
 
     pub async fn run(self) -> Result<(), Error> {
+        for jobs in self.tenants {
+            jobs.spawn();
+        }
         axum::serve(self.listener, self.app).await
     }
 
@@ -74,14 +347,166 @@ impl Application {
     }
 }
 
+/// One route of the API, listed in a table shared by the router and the OpenAPI drift test.
+pub struct ApiRoute {
+    pub method: Method,
+    pub path: &'static str,
+    handler: MethodRouter<AppState>,
+}
+
+impl ApiRoute {
+    fn new<H, T>(method: Method, path: &'static str, handler: H) -> Self
+    where
+        H: Handler<T, AppState>,
+        T: 'static,
+    {
+        let filter = MethodFilter::try_from(method.clone()).expect("routes use standard methods");
+        ApiRoute {
+            method,
+            path,
+            handler: on(filter, handler),
+        }
+    }
+}
+
+fn router(routes: Vec<ApiRoute>) -> Router<AppState> {
+    routes.into_iter().fold(Router::new(), |router, route| {
+        router.route(route.path, route.handler)
+    })
+}
+
+/// The routes of one tenant; the contest routes stay at the root, and are mirrored under `/v1`.
+fn api_routes() -> Router<AppState> {
+    Router::new()
+        .merge(router(v1_routes()))
+        .nest("/v1", router(v1_routes()))
+        .nest("/v2", router(v2_routes()))
+}
+
+/// Served outside of tenants, answering for the whole server.
+pub const HEALTH_CHECK_PATH: &str = "/health-check";
+
+/// The `(method, path)` of every route the OpenAPI document describes: the health check, the
+/// contest routes at the root and the `/v2` routes.
+pub fn documented_routes() -> Vec<(Method, String)> {
+    let v1 = v1_routes()
+        .into_iter()
+        .map(|route| (route.method, route.path.to_string()));
+    let v2 = v2_routes()
+        .into_iter()
+        .map(|route| (route.method, format!("/v2{}", route.path)));
+    std::iter::once((Method::GET, HEALTH_CHECK_PATH.to_string()))
+        .chain(v1)
+        .chain(v2)
+        .collect()
+}
+
+/// The API as the contest specified it, served both unversioned and under `/v1`.
+pub fn v1_routes() -> Vec<ApiRoute> {
+    vec![
+        ApiRoute::new(Method::GET, "/pessoas/:id", routes::devs::get_person),
+        ApiRoute::new(Method::DELETE, "/pessoas/:id", routes::devs::delete_person),
+        ApiRoute::new(
+            Method::POST,
+            "/pessoas/:id/restore",
+            routes::devs::restore_person,
+        ),
+        ApiRoute::new(
+            Method::PUT,
+            "/pessoas/:id/avatar",
+            routes::avatars::put_avatar,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/pessoas/:id/avatar",
+            routes::avatars::get_avatar,
+        ),
+        ApiRoute::new(Method::POST, "/pessoas", routes::devs::create_person),
+        ApiRoute::new(Method::GET, "/pessoas", routes::devs::search_persons),
+        ApiRoute::new(
+            Method::POST,
+            "/pessoas/bulk",
+            routes::import_devs::import_persons,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/pessoas/export",
+            routes::export_devs::export_persons,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/pessoas/stream",
+            routes::person_stream::stream_persons,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/pessoas/live",
+            routes::live_search::live_search,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/contagem-pessoas",
+            routes::count_devs::count_persons,
+        ),
+        ApiRoute::new(Method::GET, "/stacks", routes::stacks::suggest_stacks),
+        ApiRoute::new(Method::GET, "/stacks/top", routes::stacks::top_stacks),
+        ApiRoute::new(Method::POST, "/webhooks", routes::webhooks::create_webhook),
+        ApiRoute::new(Method::GET, "/webhooks", routes::webhooks::list_webhooks),
+        ApiRoute::new(Method::GET, "/webhooks/:id", routes::webhooks::get_webhook),
+        ApiRoute::new(
+            Method::PUT,
+            "/webhooks/:id",
+            routes::webhooks::update_webhook,
+        ),
+        ApiRoute::new(
+            Method::DELETE,
+            "/webhooks/:id",
+            routes::webhooks::delete_webhook,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/webhooks/:id/deliveries",
+            routes::webhooks::list_deliveries,
+        ),
+        ApiRoute::new(
+            Method::GET,
+            "/webhooks/:id/dead-letters",
+            routes::webhooks::list_dead_letters,
+        ),
+    ]
+}
+
+/// Person routes answering with [`crate::structs::api::Envelope`]s, paginating searches and
+/// describing failures with [`crate::structs::api::ErrorBody`]s.
+pub fn v2_routes() -> Vec<ApiRoute> {
+    vec![
+        ApiRoute::new(Method::GET, "/pessoas/:id", routes::v2::get_person),
+        ApiRoute::new(Method::DELETE, "/pessoas/:id", routes::v2::delete_person),
+        ApiRoute::new(
+            Method::POST,
+            "/pessoas/:id/restore",
+            routes::v2::restore_person,
+        ),
+        ApiRoute::new(Method::POST, "/pessoas", routes::v2::create_person),
+        ApiRoute::new(Method::GET, "/pessoas", routes::v2::search_persons),
+    ]
+}
+
 pub async fn get_database_connection(
     database_config: DatabaseConfiguration,
 ) -> Result<Database, mongodb::error::Error> {
-    let client_options = ClientOptions::parse(database_config.connection_string()).await?;
-    let client = Client::with_options(client_options)?;
+    let client = get_client(&database_config).await?;
     Ok(client.database(&database_config.database_name))
 }
 
+/// A client for the whole MongoDB server, not only the configured database.
+pub async fn get_client(
+    database_config: &DatabaseConfiguration,
+) -> Result<Client, mongodb::error::Error> {
+    let client_options = ClientOptions::parse(database_config.connection_string()).await?;
+    Client::with_options(client_options)
+}
+
 #[derive(Clone, Copy)]
 struct MakeRequestUuid;
 
@@ -0,0 +1,211 @@
+use std::sync::Arc;
+
+use mongodb::bson::{doc, DateTime, Document};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{FindOneAndUpdateOptions, IndexOptions, ReturnDocument};
+use mongodb::{Collection, Database, IndexModel};
+use uuid::Uuid;
+
+use crate::migrations;
+use crate::normalization::StackAliases;
+use crate::structs::person;
+
+/// The code MongoDB reports unique index violations with.
+pub const DUPLICATE_KEY_CODE: i32 = 11000;
+
+pub fn is_duplicate_key(error: &mongodb::error::Error) -> bool {
+    matches!(
+        &*error.kind,
+        ErrorKind::Write(WriteFailure::WriteError(write_error))
+            if write_error.code == DUPLICATE_KEY_CODE
+    )
+}
+
+/// Creates the indexes the `devs` collection relies on. Nicknames are unique across every stored
+/// person, soft-deleted ones included, so a nickname stays reserved until its person is purged.
+pub async fn ensure_person_indexes(client: &PersonStore) -> Result<(), mongodb::error::Error> {
+    let nickname_index = IndexModel::builder()
+        .keys(doc! {"nickname": 1})
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    client.persons().create_index(nickname_index, None).await?;
+    let deleted_index = IndexModel::builder()
+        .keys(doc! {"deleted_at": 1})
+        .options(
+            IndexOptions::builder()
+                .name(DELETED_INDEX.to_string())
+                .partial_filter_expression(deleted())
+                .build(),
+        )
+        .build();
+    client.persons().create_index(deleted_index, None).await?;
+    Ok(())
+}
+
+/// Holds only the soft-deleted persons, so counting them stays cheap however many others there
+/// are. Queries matching [`deleted`] are answered from it.
+const DELETED_INDEX: &str = "deleted_at_partial";
+
+fn deleted() -> Document {
+    doc! {"deleted_at": {"$type": "date"}}
+}
+
+/// The database of a tenant and the collection its persons are stored in.
+#[derive(Clone)]
+pub struct PersonStore {
+    database: Database,
+    collection_name: String,
+    stack_aliases: Arc<StackAliases>,
+}
+
+impl PersonStore {
+    /// `stack_aliases` canonicalize the stacks of documents stored before aliases were resolved.
+    pub fn new(
+        database: Database,
+        collection_name: impl Into<String>,
+        stack_aliases: Arc<StackAliases>,
+    ) -> Self {
+        PersonStore {
+            database,
+            collection_name: collection_name.into(),
+            stack_aliases,
+        }
+    }
+
+    /// Where everything else of the tenant is stored.
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    pub fn collection_name(&self) -> &str {
+        &self.collection_name
+    }
+
+    pub fn stack_aliases(&self) -> &Arc<StackAliases> {
+        &self.stack_aliases
+    }
+
+    pub fn persons(&self) -> Collection<person::Person> {
+        self.database.collection(&self.collection_name)
+    }
+
+    /// The persons as stored, to be read through [`migrations::read_person`].
+    pub fn stored_persons(&self) -> Collection<Document> {
+        self.database.collection(&self.collection_name)
+    }
+}
+
+#[derive(Debug)]
+pub enum InsertPersonError {
+    NicknameTaken,
+    Storage(mongodb::error::Error),
+}
+
+impl std::fmt::Display for InsertPersonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsertPersonError::NicknameTaken => write!(f, "nickname already taken"),
+            InsertPersonError::Storage(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// The person with `id`, unless it was soft-deleted.
+pub async fn find_person(
+    client: &PersonStore,
+    id: Uuid,
+) -> Result<Option<person::Person>, mongodb::error::Error> {
+    let mut filter = person::not_deleted();
+    filter.insert("_id", id);
+    let stored_persons = client.stored_persons();
+    match stored_persons.find_one(filter, None).await? {
+        Some(stored) => Ok(Some(
+            migrations::read_and_store_person(client, stored).await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// How many persons there are, the soft-deleted ones aside. Taken from the collection metadata
+/// less the soft-deleted persons counted on their index, rather than by scanning every person.
+pub async fn count_persons(client: &PersonStore) -> Result<u64, mongodb::error::Error> {
+    let devs_store = client.persons();
+    let stored = devs_store.estimated_document_count(None).await?;
+    let deleted = devs_store.count_documents(deleted(), None).await?;
+    Ok(stored.saturating_sub(deleted))
+}
+
+/// Stores a new person unless its nickname is taken.
+pub async fn insert_person(
+    client: &PersonStore,
+    dev: &person::Person,
+) -> Result<(), InsertPersonError> {
+    // The unique index settles races; checking first keeps the common case off the error path.
+    let taken = client
+        .persons()
+        .count_documents(doc! {"nickname": &dev.nickname}, None)
+        .await
+        .map_err(InsertPersonError::Storage)?;
+    if taken > 0 {
+        return Err(InsertPersonError::NicknameTaken);
+    }
+    match client.persons().insert_one(dev, None).await {
+        Ok(_) => Ok(()),
+        Err(error) if is_duplicate_key(&error) => Err(InsertPersonError::NicknameTaken),
+        Err(error) => Err(InsertPersonError::Storage(error)),
+    }
+}
+
+/// Hides a person until it is restored or purged, returning whether there was one to hide.
+pub async fn soft_delete_person(
+    client: &PersonStore,
+    id: Uuid,
+) -> Result<bool, mongodb::error::Error> {
+    let mut filter = person::not_deleted();
+    filter.insert("_id", id);
+    let deleted = client
+        .persons()
+        .update_one(filter, doc! {"$set": {"deleted_at": DateTime::now()}}, None)
+        .await?;
+    Ok(deleted.matched_count > 0)
+}
+
+/// Undoes [`soft_delete_person`], returning the restored person if it was deleted.
+pub async fn restore_person(
+    client: &PersonStore,
+    id: Uuid,
+) -> Result<Option<person::Person>, mongodb::error::Error> {
+    let options = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+    let stored_persons = client.stored_persons();
+    let restored = stored_persons
+        .find_one_and_update(
+            doc! {"_id": id, "deleted_at": {"$ne": null}},
+            doc! {"$unset": {"deleted_at": ""}},
+            options,
+        )
+        .await?;
+    match restored {
+        Some(stored) => Ok(Some(
+            migrations::read_and_store_person(client, stored).await?,
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Records a person's newly uploaded avatar, returning whether the person exists.
+pub async fn set_avatar(
+    client: &PersonStore,
+    id: Uuid,
+    avatar: &person::Avatar,
+) -> Result<bool, mongodb::error::Error> {
+    let mut filter = person::not_deleted();
+    filter.insert("_id", id);
+    let avatar = mongodb::bson::to_bson(avatar)?;
+    let updated = client
+        .persons()
+        .update_one(filter, doc! {"$set": {"avatar": avatar}}, None)
+        .await?;
+    Ok(updated.matched_count > 0)
+}
@@ -1,2 +1,4 @@
 pub mod api;
+pub mod idempotency;
 pub mod person;
+pub mod webhook;
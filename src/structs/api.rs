@@ -1,35 +1,403 @@
-use chrono::NaiveDate;
+use crate::export::ExportFormat;
+use chrono::{NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+/// Also accepted with the English field names of [`EnglishPersonBody`].
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
 pub struct CreatePersonBody {
-    #[serde(rename(deserialize = "apelido", serialize = "apelido"))]
+    #[serde(rename = "apelido", alias = "nickname")]
     pub nickname: String,
-    #[serde(rename(deserialize = "nome", serialize = "nome"))]
+    #[serde(rename = "nome", alias = "name")]
     pub name: String,
-    #[serde(rename(deserialize = "nascimento", serialize = "nascimento"))]
+    #[serde(rename = "nascimento", alias = "birth_date")]
     pub birth_date: NaiveDate,
-    #[serde(rename(deserialize = "stack", serialize = "stack"))]
-    pub stacks: Option<Vec<String>>,
+    #[serde(rename = "stack", alias = "stacks")]
+    pub stacks: Option<Vec<StackInput>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(
+        default,
+        rename = "localizacao",
+        alias = "location",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub location: Option<String>,
+    #[serde(
+        default,
+        rename = "senioridade",
+        alias = "seniority",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub seniority: Option<Seniority>,
+    /// GitHub handle, without the `@`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Seniority {
+    Junior,
+    Mid,
+    Senior,
+    Lead,
+}
+
+/// A submitted stack: either just its name, or a [`StackEntryBody`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(untagged)]
+pub enum StackInput {
+    Name(String),
+    Entry(StackEntryBody),
+}
+
+impl StackInput {
+    pub fn into_entry(self) -> StackEntryBody {
+        match self {
+            StackInput::Name(name) => StackEntryBody {
+                name,
+                level: None,
+                years: None,
+            },
+            StackInput::Entry(entry) => entry,
+        }
+    }
+}
+
+impl From<&str> for StackInput {
+    fn from(name: &str) -> Self {
+        StackInput::Name(name.to_string())
+    }
 }
 
-#[derive(Debug, Default, Deserialize)]
+/// A stack with how well, and for how long, the person knows it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct StackEntryBody {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<StackLevel>,
+    /// Years of experience.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub years: Option<u32>,
+}
+
+/// Proficiency in a stack, from the lowest.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StackLevel {
+    Beginner,
+    Junior,
+    Mid,
+    Senior,
+    Expert,
+}
+
+impl StackLevel {
+    pub const ALL: [StackLevel; 5] = [
+        StackLevel::Beginner,
+        StackLevel::Junior,
+        StackLevel::Mid,
+        StackLevel::Senior,
+        StackLevel::Expert,
+    ];
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchPersonQuery {
-    #[serde(rename(deserialize = "t"))]
-    pub search_term: String,
+    #[serde(rename = "t")]
+    pub search_term: Option<String>,
+    /// Only persons with this stack, or one of its aliases.
+    pub stack: Option<String>,
+    /// Only the person with this exact nickname, ignoring case.
+    pub nickname: Option<String>,
+    pub born_after: Option<NaiveDate>,
+    pub born_before: Option<NaiveDate>,
+    /// Only persons at least this many years old.
+    pub min_age: Option<u32>,
+    /// Only persons at most this many years old.
+    pub max_age: Option<u32>,
+    /// Only persons whose location contains this, ignoring case.
+    pub location: Option<String>,
+    pub seniority: Option<Seniority>,
+    /// Only persons knowing a stack at least this well; the one in `stack` when given.
+    pub min_level: Option<StackLevel>,
+    /// Only persons with at least this many years of a stack; the one in `stack` when given.
+    pub min_years: Option<u32>,
+    pub sort: Option<SearchSort>,
+    /// Also match terms one typo away.
+    pub fuzzy: Option<bool>,
+    /// Include each person's relevance `score` in the response.
+    pub explain: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, ToSchema)]
+pub enum SearchSort {
+    /// Paginated searches only rank their first 10000 matches.
+    #[default]
+    #[serde(rename = "relevance")]
+    Relevance,
+    #[serde(rename = "nickname")]
+    NicknameAscending,
+    #[serde(rename = "-nickname")]
+    NicknameDescending,
+    #[serde(rename = "name")]
+    NameAscending,
+    #[serde(rename = "-name")]
+    NameDescending,
+    #[serde(rename = "birth_date")]
+    BirthDateAscending,
+    #[serde(rename = "-birth_date")]
+    BirthDateDescending,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub struct PersonBody {
-    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    /// Serialized as a hyphenated string, unlike the BSON binary persons are stored with.
     pub id: Uuid,
-    #[serde(rename(serialize = "apelido", deserialize = "apelido"))]
+    #[serde(rename = "apelido")]
     pub nickname: String,
-    #[serde(rename(serialize = "nome", deserialize = "nome"))]
+    #[serde(rename = "nome")]
     pub name: String,
-    #[serde(rename(serialize = "nascimento", deserialize = "nascimento"))]
+    #[serde(rename = "nascimento")]
     pub birth_date: NaiveDate,
-    #[serde(rename(serialize = "stack", deserialize = "stack"))]
+    #[serde(rename = "stack")]
     pub stacks: Option<Vec<String>>,
+    /// Only set when a level or years were given for some stack.
+    #[serde(
+        default,
+        rename = "stack_detalhada",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stack_details: Option<Vec<StackEntryBody>>,
+    /// Computed from the birth date when the body is rendered.
+    #[serde(default, rename = "idade")]
+    pub age: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(
+        default,
+        rename = "localizacao",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub location: Option<String>,
+    #[serde(
+        default,
+        rename = "senioridade",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub seniority: Option<Seniority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ScoredPersonBody {
+    #[serde(flatten)]
+    pub person: PersonBody,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+/// [`PersonBody`] with English field names, see [`crate::field_naming`].
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct EnglishPersonBody {
+    pub id: Uuid,
+    pub nickname: String,
+    pub name: String,
+    pub birth_date: NaiveDate,
+    pub stacks: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_details: Option<Vec<StackEntryBody>>,
+    #[serde(default)]
+    pub age: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seniority: Option<Seniority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct EnglishScoredPersonBody {
+    #[serde(flatten)]
+    pub person: EnglishPersonBody,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportPersonsQuery {
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AvatarQuery {
+    /// One of the thumbnail sizes; the original is sent when missing.
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct PersonCountBody {
+    pub total: u64,
+    pub by_stack: BTreeMap<String, u64>,
+    pub by_birth_year: BTreeMap<String, u64>,
+    pub by_creation_day: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StackQuery {
+    pub prefix: Option<String>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct StackCountBody {
+    pub stack: String,
+    pub count: u64,
+}
+
+/// A message sent by live search clients.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum LiveSearchRequest {
+    Subscribe { term: String },
+    Unsubscribe { term: String },
+}
+
+/// A message pushed to live search clients.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveSearchEvent {
+    /// The persons already matching `term` when subscribing, as `GET /pessoas?t=` returns them.
+    Results {
+        term: String,
+        persons: Vec<PersonBody>,
+    },
+    /// A person created after subscribing to `term` that matches it.
+    Match {
+        term: String,
+        person: PersonBody,
+    },
+    /// Matches were dropped because the client was reading too slowly.
+    Lagged {
+        skipped: u64,
+    },
+    Error {
+        message: String,
+    },
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, ToSchema)]
+pub struct CreateWebhookBody {
+    pub url: String,
+    /// Generated when missing.
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct WebhookBody {
+    pub id: Uuid,
+    pub url: String,
+    /// Only returned when the webhook is created or its secret changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+/// The body POSTed to webhooks.
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct WebhookPayload {
+    pub event: String,
+    pub person: PersonBody,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct WebhookDeliveryLogBody {
+    pub id: Uuid,
+    pub delivery_id: Uuid,
+    pub attempt: u32,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub attempted_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct WebhookDeadLetterBody {
+    pub id: Uuid,
+    pub event: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+/// A person event as published by the outbox relay. Delivery is at least once, so consumers should
+/// skip event ids they have already seen.
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct PersonEventBody {
+    pub id: Uuid,
+    pub event: String,
+    pub occurred_at: chrono::DateTime<Utc>,
+    pub person: PersonBody,
+}
+
+/// The body of successful `/v2` responses.
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+#[aliases(
+    PersonEnvelope = Envelope<PersonBody>,
+    EnglishPersonEnvelope = Envelope<EnglishPersonBody>,
+    PersonPageEnvelope = Envelope<Vec<ScoredPersonBody>>,
+    EnglishPersonPageEnvelope = Envelope<Vec<EnglishScoredPersonBody>>,
+)]
+pub struct Envelope<T> {
+    pub data: T,
+    /// Only set on paginated responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<PageMeta>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct PageMeta {
+    pub page: u32,
+    pub per_page: u32,
+    /// Results across every page.
+    pub total: u64,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageQuery {
+    /// Starting from 1.
+    pub page: Option<u32>,
+    /// From 1 to 100, 20 by default.
+    pub per_page: Option<u32>,
+}
+
+/// The body of failed `/v2` responses.
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ErrorDetail {
+    /// Stable and machine-readable, e.g. `nickname_taken`.
+    pub code: String,
+    pub message: String,
 }
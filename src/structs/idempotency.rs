@@ -0,0 +1,22 @@
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// A request made with an `Idempotency-Key`, stored in `idempotency_keys` until `expires_at`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct IdempotencyRecord {
+    #[serde(rename(serialize = "_id", deserialize = "_id"))]
+    pub key: String,
+    /// Hex SHA-256 of the raw request body.
+    pub request_hash: String,
+    /// Missing while the original request is still being handled.
+    pub response: Option<StoredResponse>,
+    pub created_at: DateTime,
+    pub expires_at: DateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub location: String,
+    pub body: String,
+}
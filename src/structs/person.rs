@@ -1,4 +1,10 @@
+use crate::age;
+use crate::events::PERSON_CREATED_EVENT;
+use crate::migrations;
+use crate::normalization::{fold_text, StackAliases};
+use crate::structs::api;
 use chrono::NaiveDate;
+use mongodb::bson::{doc, DateTime, Document};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -7,8 +13,184 @@ pub struct Person {
     #[serde(rename(serialize = "_id", deserialize = "_id"))]
     #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
     pub id: Uuid,
+    /// See [`migrations::CURRENT_SCHEMA_VERSION`].
+    #[serde(default)]
+    pub schema_version: u32,
     pub nickname: String,
     pub name: String,
     pub birth_date: NaiveDate,
+    /// Canonical stack names, see [`StackAliases::canonicalize`].
     pub stacks: Option<Vec<String>>,
+    /// Stack names as originally submitted, kept for display.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_labels: Option<Vec<String>>,
+    /// One per stack, in the same order as `stacks`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack_entries: Option<Vec<StackEntry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime>,
+    /// Nickname, name and stacks folded with [`fold_text`], matched by accent-insensitive search.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_terms: Option<Vec<String>>,
+    /// Events not yet handed to the outbox sink, stored in the person document so that both are
+    /// written atomically.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbox: Option<Vec<OutboxEvent>>,
+    /// Set while a relay is publishing the outbox, so no other relay picks it up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbox_locked_until: Option<DateTime>,
+    /// When the person was soft-deleted; it can be restored until the purge job removes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seniority: Option<api::Seniority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bio: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar: Option<Avatar>,
+}
+
+/// A stack of a person, whether it was submitted as a plain name or with details.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct StackEntry {
+    /// Canonical name, see [`StackAliases::canonicalize`].
+    pub stack: String,
+    /// Name as originally submitted.
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<api::StackLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub years: Option<u32>,
+}
+
+/// What is known of a person's avatar; the images themselves live in the avatar blob store.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Avatar {
+    /// Of the original; thumbnails are always PNG.
+    pub content_type: String,
+    /// SHA-256 of the original, in hex, which its entity tags are made of.
+    pub digest: String,
+    /// The thumbnails made when it was uploaded, which may differ from the configured ones.
+    pub thumbnail_sizes: Vec<u32>,
+    pub updated_at: DateTime,
+}
+
+/// Matches the persons that were not soft-deleted.
+pub fn not_deleted() -> Document {
+    doc! {"deleted_at": null}
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct OutboxEvent {
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub id: Uuid,
+    pub event: String,
+    pub occurred_at: DateTime,
+}
+
+impl Person {
+    pub fn new(body: api::CreatePersonBody, stack_aliases: &StackAliases) -> Self {
+        let created_at = DateTime::now();
+        let stack_entries: Option<Vec<StackEntry>> = body.stacks.map(|stacks| {
+            stacks
+                .into_iter()
+                .map(|stack| {
+                    let entry = stack.into_entry();
+                    StackEntry {
+                        stack: stack_aliases.canonicalize(&entry.name),
+                        label: entry.name,
+                        level: entry.level,
+                        years: entry.years,
+                    }
+                })
+                .collect()
+        });
+        let mut dev = Person {
+            id: Uuid::new_v4(),
+            schema_version: migrations::CURRENT_SCHEMA_VERSION,
+            name: body.name,
+            nickname: body.nickname,
+            birth_date: body.birth_date,
+            stacks: stack_entries
+                .as_ref()
+                .map(|entries| entries.iter().map(|entry| entry.stack.clone()).collect()),
+            stack_labels: stack_entries
+                .as_ref()
+                .map(|entries| entries.iter().map(|entry| entry.label.clone()).collect()),
+            stack_entries,
+            created_at: Some(created_at),
+            search_terms: None,
+            outbox: Some(vec![OutboxEvent {
+                id: Uuid::new_v4(),
+                event: PERSON_CREATED_EVENT.to_string(),
+                occurred_at: created_at,
+            }]),
+            outbox_locked_until: None,
+            deleted_at: None,
+            email: body.email,
+            location: body.location,
+            seniority: body.seniority,
+            github: body.github,
+            bio: body.bio,
+            avatar: None,
+        };
+        dev.search_terms = Some(dev.fold_search_terms());
+        dev
+    }
+
+    pub fn fold_search_terms(&self) -> Vec<String> {
+        [&self.nickname, &self.name]
+            .into_iter()
+            .chain(self.stacks.iter().flatten())
+            .chain(self.stack_labels.iter().flatten())
+            .map(|term| fold_text(term))
+            .collect()
+    }
+
+    /// Stacks as they should be shown, falling back to the stored ones for documents
+    /// created before labels were kept.
+    pub fn display_stacks(&self) -> Option<&Vec<String>> {
+        self.stack_labels.as_ref().or(self.stacks.as_ref())
+    }
+}
+
+impl From<Person> for api::PersonBody {
+    fn from(dev: Person) -> Self {
+        api::PersonBody {
+            id: dev.id,
+            name: dev.name,
+            nickname: dev.nickname,
+            birth_date: dev.birth_date,
+            age: age::age_on(dev.birth_date, age::today()),
+            stacks: dev.stack_labels.or(dev.stacks),
+            stack_details: dev
+                .stack_entries
+                .filter(|entries| {
+                    entries
+                        .iter()
+                        .any(|entry| entry.level.is_some() || entry.years.is_some())
+                })
+                .map(|entries| {
+                    entries
+                        .into_iter()
+                        .map(|entry| api::StackEntryBody {
+                            name: entry.label,
+                            level: entry.level,
+                            years: entry.years,
+                        })
+                        .collect()
+                }),
+            email: dev.email,
+            location: dev.location,
+            seniority: dev.seniority,
+            github: dev.github,
+            bio: dev.bio,
+        }
+    }
 }
@@ -0,0 +1,92 @@
+use crate::structs::api;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Webhook {
+    #[serde(rename(serialize = "_id", deserialize = "_id"))]
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub id: Uuid,
+    pub url: String,
+    /// Key of the HMAC-SHA256 signature sent with every delivery.
+    pub secret: String,
+    pub created_at: DateTime,
+}
+
+/// A payload waiting to be POSTed to a webhook, kept in `webhook_deliveries` until it is
+/// delivered or moved to `webhook_dead_letters` after too many failed attempts.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WebhookDelivery {
+    #[serde(rename(serialize = "_id", deserialize = "_id"))]
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub id: Uuid,
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub webhook_id: Uuid,
+    pub event: String,
+    /// The JSON body exactly as sent and signed.
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime,
+    /// Set while a worker is attempting the delivery, so no other worker picks it up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_until: Option<DateTime>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub created_at: DateTime,
+}
+
+/// The outcome of one delivery attempt.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WebhookDeliveryLog {
+    #[serde(rename(serialize = "_id", deserialize = "_id"))]
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub id: Uuid,
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub delivery_id: Uuid,
+    #[serde(with = "mongodb::bson::serde_helpers::uuid_1_as_binary")]
+    pub webhook_id: Uuid,
+    pub attempt: u32,
+    /// The receiver's response status, if it answered at all.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub attempted_at: DateTime,
+}
+
+impl From<Webhook> for api::WebhookBody {
+    fn from(webhook: Webhook) -> Self {
+        api::WebhookBody {
+            id: webhook.id,
+            url: webhook.url,
+            secret: None,
+            created_at: webhook.created_at.to_chrono(),
+        }
+    }
+}
+
+impl From<WebhookDelivery> for api::WebhookDeadLetterBody {
+    fn from(delivery: WebhookDelivery) -> Self {
+        api::WebhookDeadLetterBody {
+            id: delivery.id,
+            event: delivery.event,
+            attempts: delivery.attempts,
+            last_error: delivery.last_error,
+            created_at: delivery.created_at.to_chrono(),
+        }
+    }
+}
+
+impl From<WebhookDeliveryLog> for api::WebhookDeliveryLogBody {
+    fn from(log: WebhookDeliveryLog) -> Self {
+        api::WebhookDeliveryLogBody {
+            id: log.id,
+            delivery_id: log.delivery_id,
+            attempt: log.attempt,
+            status: log.status,
+            error: log.error,
+            duration_ms: log.duration_ms,
+            attempted_at: log.attempted_at.to_chrono(),
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use tower::ServiceExt;
+
+use crate::configuration::TenantResolution;
+
+/// The API of every tenant, each router holding only the state of its own tenant, so that a
+/// request can never reach the databases of another one.
+pub struct TenantRouter {
+    resolution: TenantResolution,
+    routers: HashMap<String, Router>,
+}
+
+impl TenantRouter {
+    pub fn new(resolution: TenantResolution, routers: HashMap<String, Router>) -> Self {
+        TenantRouter {
+            resolution,
+            routers,
+        }
+    }
+
+    /// The tenant a request with `headers` names, known or not, lowercased like the configured
+    /// ids.
+    pub fn tenant_id(&self, headers: &HeaderMap) -> Option<String> {
+        let tenant_id = match &self.resolution {
+            TenantResolution::Header { name } => headers.get(name)?.to_str().ok()?,
+            TenantResolution::Subdomain => subdomain(headers.get(header::HOST)?.to_str().ok()?)?,
+        };
+        Some(tenant_id.to_lowercase())
+    }
+}
+
+/// The leftmost label of `host` when it has more than one, ignoring the port.
+pub fn subdomain(host: &str) -> Option<&str> {
+    let hostname = host.split_once(':').map_or(host, |(hostname, _)| hostname);
+    let (label, parent) = hostname.split_once('.')?;
+    (!label.is_empty() && !parent.is_empty()).then_some(label)
+}
+
+/// Where the files of `tenant_id` are kept under `path`; `path` itself without tenants.
+pub fn tenant_path(path: &Path, tenant_id: Option<&str>) -> PathBuf {
+    match tenant_id {
+        Some(tenant_id) => path.join(tenant_id),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Hands a request to the router of its tenant: 400 when it names none, 404 when it is unknown.
+pub async fn route_to_tenant(
+    State(tenants): State<Arc<TenantRouter>>,
+    request: Request,
+) -> Response {
+    let Some(tenant_id) = tenants.tenant_id(request.headers()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let Some(router) = tenants.routers.get(&tenant_id).cloned() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(never) => match never {},
+    }
+}
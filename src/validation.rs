@@ -0,0 +1,154 @@
+use chrono::NaiveDate;
+
+use crate::age;
+use crate::configuration::BirthDateConfiguration;
+use crate::structs::api;
+
+pub const MAX_EMAIL_LENGTH: usize = 254;
+pub const MAX_LOCATION_LENGTH: usize = 100;
+pub const MAX_GITHUB_HANDLE_LENGTH: usize = 39;
+pub const MAX_BIO_LENGTH: usize = 500;
+pub const MAX_STACK_YEARS: u32 = 80;
+
+/// A person body that deserialized fine but breaks a rule of one of its fields.
+#[derive(Debug, PartialEq)]
+pub enum InvalidPerson {
+    Email,
+    Location,
+    GithubHandle,
+    Bio,
+    StackYears,
+    BirthDateInFuture,
+    TooYoung { min_age_years: u32 },
+    TooOld { max_age_years: u32 },
+}
+
+impl std::fmt::Display for InvalidPerson {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidPerson::Email => write!(f, "email must be a valid address"),
+            InvalidPerson::Location => write!(
+                f,
+                "location must have 1 to {} characters",
+                MAX_LOCATION_LENGTH
+            ),
+            InvalidPerson::GithubHandle => write!(
+                f,
+                "github must be a GitHub handle: up to {} letters, digits or single inner hyphens",
+                MAX_GITHUB_HANDLE_LENGTH
+            ),
+            InvalidPerson::Bio => write!(f, "bio must have at most {} characters", MAX_BIO_LENGTH),
+            InvalidPerson::BirthDateInFuture => write!(f, "birth date must not be in the future"),
+            InvalidPerson::TooYoung { min_age_years } => {
+                write!(f, "persons must be at least {} years old", min_age_years)
+            }
+            InvalidPerson::TooOld { max_age_years } => {
+                write!(f, "persons must be at most {} years old", max_age_years)
+            }
+            InvalidPerson::StackYears => {
+                write!(f, "stack years must be at most {}", MAX_STACK_YEARS)
+            }
+        }
+    }
+}
+
+/// The ages persons may have, see [`BirthDateConfiguration`].
+#[derive(Debug)]
+pub struct BirthDateRules {
+    min_age_years: u32,
+    max_age_years: u32,
+}
+
+impl BirthDateRules {
+    pub fn new(birth_date_config: &BirthDateConfiguration) -> Self {
+        BirthDateRules {
+            min_age_years: birth_date_config.min_age_years,
+            max_age_years: birth_date_config.max_age_years,
+        }
+    }
+
+    pub fn check(&self, birth_date: NaiveDate, today: NaiveDate) -> Result<(), InvalidPerson> {
+        if birth_date > today {
+            return Err(InvalidPerson::BirthDateInFuture);
+        }
+        let age = age::age_on(birth_date, today);
+        if age < self.min_age_years {
+            return Err(InvalidPerson::TooYoung {
+                min_age_years: self.min_age_years,
+            });
+        }
+        if age > self.max_age_years {
+            return Err(InvalidPerson::TooOld {
+                max_age_years: self.max_age_years,
+            });
+        }
+        Ok(())
+    }
+}
+
+pub fn validate_person(
+    body: &api::CreatePersonBody,
+    birth_date_rules: &BirthDateRules,
+) -> Result<(), InvalidPerson> {
+    birth_date_rules.check(body.birth_date, age::today())?;
+    if body
+        .email
+        .as_deref()
+        .is_some_and(|email| !is_valid_email(email))
+    {
+        return Err(InvalidPerson::Email);
+    }
+    if body.location.as_deref().is_some_and(|location| {
+        location.trim().is_empty() || location.chars().count() > MAX_LOCATION_LENGTH
+    }) {
+        return Err(InvalidPerson::Location);
+    }
+    if body
+        .github
+        .as_deref()
+        .is_some_and(|handle| !is_valid_github_handle(handle))
+    {
+        return Err(InvalidPerson::GithubHandle);
+    }
+    if body
+        .bio
+        .as_deref()
+        .is_some_and(|bio| bio.chars().count() > MAX_BIO_LENGTH)
+    {
+        return Err(InvalidPerson::Bio);
+    }
+    if body.stacks.iter().flatten().any(|stack| {
+        matches!(stack, api::StackInput::Entry(api::StackEntryBody { years: Some(years), .. }) if *years > MAX_STACK_YEARS)
+    }) {
+        return Err(InvalidPerson::StackYears);
+    }
+    Ok(())
+}
+
+/// A pragmatic check rather than RFC 5322: a local part, an `@` and a dotted domain, without
+/// whitespace.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    email.len() <= MAX_EMAIL_LENGTH
+        && !local.is_empty()
+        && !domain.contains('@')
+        && !email.chars().any(|character| character.is_whitespace())
+        && domain
+            .split('.')
+            .all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+        && domain.contains('.')
+}
+
+/// GitHub's own rule, `^[A-Za-z0-9](-?[A-Za-z0-9])*$` with at most 39 characters.
+pub fn is_valid_github_handle(handle: &str) -> bool {
+    handle.len() <= MAX_GITHUB_HANDLE_LENGTH
+        && !handle.starts_with('-')
+        && !handle.ends_with('-')
+        && !handle.contains("--")
+        && !handle.is_empty()
+        && handle
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '-')
+}
@@ -0,0 +1,467 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use futures::stream::TryStreamExt;
+use hmac::{Hmac, Mac};
+use mongodb::bson::{doc, DateTime};
+use mongodb::options::{FindOneAndUpdateOptions, ReplaceOptions, ReturnDocument};
+use mongodb::{Collection, Database, IndexModel};
+use sha2::Sha256;
+use tokio::sync::{Notify, Semaphore};
+use uuid::Uuid;
+
+use crate::configuration::WebhookConfiguration;
+use crate::outbox::{EventSink, SinkError};
+use crate::storage;
+use crate::structs::api;
+use crate::structs::webhook::{Webhook, WebhookDelivery, WebhookDeliveryLog};
+
+pub const WEBHOOKS_COLLECTION: &str = "webhooks";
+pub const DELIVERIES_COLLECTION: &str = "webhook_deliveries";
+pub const DELIVERY_LOGS_COLLECTION: &str = "webhook_delivery_logs";
+pub const DEAD_LETTERS_COLLECTION: &str = "webhook_dead_letters";
+
+pub const EVENT_HEADER: &str = "x-webhook-event";
+pub const DELIVERY_HEADER: &str = "x-webhook-delivery";
+/// `sha256=` followed by the hex HMAC-SHA256 of the body, keyed with the webhook secret.
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Deliveries attempted at the same time, so one slow receiver does not hold back the others.
+const MAX_CONCURRENT_DELIVERIES: usize = 16;
+
+/// How long subscriptions are cached; changes made through this server invalidate them right
+/// away, this bounds how long those made through another instance go unnoticed.
+const SUBSCRIPTIONS_TTL: Duration = Duration::from_secs(5);
+
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether webhooks may be delivered to `url`: an http(s) URL with a host which, unless
+/// `allow_private_hosts`, names neither this machine nor a private network.
+pub fn is_allowed_url(url: &str, allow_private_hosts: bool) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    let Some(host) = url.host_str().filter(|host| !host.is_empty()) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    if allow_private_hosts {
+        return true;
+    }
+    match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) => is_public(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // 100.64.0.0/10 is shared by carrier-grade NATs.
+            let is_shared = first == 100 && second & 0xc0 == 64;
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || is_shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                let is_unique_local = first & 0xfe00 == 0xfc00;
+                let is_link_local = first & 0xffc0 == 0xfe80;
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || is_unique_local
+                    || is_link_local)
+            }
+        },
+    }
+}
+
+/// Whether the host of `url` only resolves to public addresses. Checked again on every delivery,
+/// since a domain may be pointed at a private address after its webhook was accepted.
+async fn resolves_publicly(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let resolved = tokio::net::lookup_host((host, port)).await;
+    match resolved {
+        Ok(addresses) => {
+            let addresses: Vec<_> = addresses.collect();
+            !addresses.is_empty() && addresses.iter().all(|address| is_public(address.ip()))
+        }
+        Err(_) => false,
+    }
+}
+
+/// Indexes the pending deliveries by due date, keeping the worker's claims cheap.
+pub async fn ensure_index(client: &Database) -> Result<(), mongodb::error::Error> {
+    let due_index = IndexModel::builder()
+        .keys(doc! {"next_attempt_at": 1})
+        .build();
+    let deliveries_store: Collection<WebhookDelivery> = client.collection(DELIVERIES_COLLECTION);
+    deliveries_store.create_index(due_index, None).await?;
+    Ok(())
+}
+
+/// Stores a delivery for every webhook when person events are relayed, and wakes up the worker.
+#[derive(Clone)]
+pub struct WebhookQueue {
+    client: Database,
+    allow_private_hosts: bool,
+    wake: Arc<Notify>,
+    subscriptions: Arc<Mutex<Subscriptions>>,
+}
+
+/// The webhooks as last loaded. `generation` changes on every invalidation, so that a load racing
+/// with one is not cached.
+#[derive(Default)]
+struct Subscriptions {
+    generation: u64,
+    loaded: Option<(Instant, Arc<Vec<Webhook>>)>,
+}
+
+impl WebhookQueue {
+    pub fn new(client: Database, webhook_config: &WebhookConfiguration) -> Self {
+        WebhookQueue {
+            client,
+            allow_private_hosts: webhook_config.allow_private_hosts,
+            wake: Arc::new(Notify::new()),
+            subscriptions: Arc::new(Mutex::new(Subscriptions::default())),
+        }
+    }
+
+    fn lock_subscriptions(&self) -> std::sync::MutexGuard<'_, Subscriptions> {
+        self.subscriptions
+            .lock()
+            .expect("webhook subscriptions poisoned")
+    }
+
+    /// Whether webhooks may be created for `url`, see [`is_allowed_url`].
+    pub fn accepts_url(&self, url: &str) -> bool {
+        is_allowed_url(url, self.allow_private_hosts)
+    }
+
+    /// Forgets the cached webhooks, after they were created, changed or deleted.
+    pub fn invalidate(&self) {
+        let mut subscriptions = self.lock_subscriptions();
+        subscriptions.generation += 1;
+        subscriptions.loaded = None;
+    }
+
+    async fn webhooks(&self) -> Result<Arc<Vec<Webhook>>, mongodb::error::Error> {
+        let generation = {
+            let subscriptions = self.lock_subscriptions();
+            match &subscriptions.loaded {
+                Some((loaded_at, webhooks)) if loaded_at.elapsed() < SUBSCRIPTIONS_TTL => {
+                    return Ok(webhooks.clone())
+                }
+                _ => subscriptions.generation,
+            }
+        };
+        let webhooks_store: Collection<Webhook> = self.client.collection(WEBHOOKS_COLLECTION);
+        let webhooks: Arc<Vec<Webhook>> =
+            Arc::new(webhooks_store.find(None, None).await?.try_collect().await?);
+        let mut subscriptions = self.lock_subscriptions();
+        if subscriptions.generation == generation {
+            subscriptions.loaded = Some((Instant::now(), webhooks.clone()));
+        }
+        Ok(webhooks)
+    }
+
+    /// Stores a delivery of `event` for every webhook. Delivery ids derive from the event and
+    /// the webhook, so enqueueing an event again does not queue it twice.
+    pub async fn enqueue(
+        &self,
+        event: &api::PersonEventBody,
+    ) -> Result<usize, mongodb::error::Error> {
+        let webhooks = self.webhooks().await?;
+        if webhooks.is_empty() {
+            return Ok(0);
+        }
+
+        let now = DateTime::now();
+        let payload = serde_json::to_string(&api::WebhookPayload {
+            event: event.event.clone(),
+            person: event.person.clone(),
+        })
+        .expect("person payloads always serialize");
+        let deliveries_store: Collection<WebhookDelivery> =
+            self.client.collection(DELIVERIES_COLLECTION);
+        let mut enqueued = 0;
+        for webhook in webhooks.iter() {
+            let delivery = WebhookDelivery {
+                id: Uuid::new_v5(&event.id, webhook.id.as_bytes()),
+                webhook_id: webhook.id,
+                event: event.event.clone(),
+                payload: payload.clone(),
+                attempts: 0,
+                next_attempt_at: now,
+                locked_until: None,
+                last_error: None,
+                created_at: now,
+            };
+            match deliveries_store.insert_one(&delivery, None).await {
+                Err(error) if storage::is_duplicate_key(&error) => {}
+                result => {
+                    result?;
+                    enqueued += 1;
+                }
+            }
+        }
+        self.wake.notify_one();
+        Ok(enqueued)
+    }
+}
+
+/// Webhooks hear about persons through the outbox relay, so that a delivery is queued even when
+/// the server stops right after storing the person.
+impl EventSink for WebhookQueue {
+    fn publish<'a>(
+        &'a self,
+        event: &'a api::PersonEventBody,
+    ) -> BoxFuture<'a, Result<(), SinkError>> {
+        Box::pin(async move {
+            self.enqueue(event).await?;
+            Ok(())
+        })
+    }
+}
+
+/// Attempts the queued deliveries until they succeed, retrying failures with exponential backoff
+/// and moving them to `webhook_dead_letters` after `max_attempts`. Deliveries are claimed with a
+/// lock in storage, so several server instances can run workers side by side.
+#[derive(Clone)]
+pub struct WebhookWorker {
+    queue: WebhookQueue,
+    http: reqwest::Client,
+    in_flight: Arc<Semaphore>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    poll_interval: Duration,
+    request_timeout: Duration,
+}
+
+impl WebhookWorker {
+    pub fn new(queue: WebhookQueue, webhook_config: &WebhookConfiguration) -> Self {
+        WebhookWorker {
+            queue,
+            http: reqwest::Client::new(),
+            in_flight: Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES)),
+            max_attempts: webhook_config.max_attempts.max(1),
+            initial_backoff: Duration::from_millis(webhook_config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(webhook_config.max_backoff_ms),
+            poll_interval: Duration::from_millis(webhook_config.poll_interval_ms),
+            request_timeout: Duration::from_millis(webhook_config.request_timeout_ms),
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            let permit = self
+                .in_flight
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("delivery semaphore is never closed");
+            match self.claim_next().await {
+                Ok(Some(delivery)) => {
+                    let worker = self.clone();
+                    tokio::spawn(async move {
+                        worker.deliver(delivery).await;
+                        drop(permit);
+                    });
+                    continue;
+                }
+                Ok(None) => {}
+                Err(error) => println!("webhooks: {}", error),
+            }
+            drop(permit);
+            tokio::select! {
+                _ = self.queue.wake.notified() => {}
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    fn deliveries_store(&self) -> Collection<WebhookDelivery> {
+        self.queue.client.collection(DELIVERIES_COLLECTION)
+    }
+
+    async fn claim_next(&self) -> Result<Option<WebhookDelivery>, mongodb::error::Error> {
+        let now = DateTime::now();
+        // A lock outliving the request timeout means the worker holding it went away.
+        let locked_until = DateTime::from_millis(
+            now.timestamp_millis() + 2 * self.request_timeout.as_millis() as i64,
+        );
+        let options = FindOneAndUpdateOptions::builder()
+            .sort(doc! {"next_attempt_at": 1})
+            .return_document(ReturnDocument::After)
+            .build();
+        self.deliveries_store()
+            .find_one_and_update(
+                doc! {
+                    "next_attempt_at": {"$lte": now},
+                    "$or": [{"locked_until": null}, {"locked_until": {"$lt": now}}],
+                },
+                doc! {"$set": {"locked_until": locked_until}},
+                options,
+            )
+            .await
+    }
+
+    async fn deliver(&self, delivery: WebhookDelivery) {
+        if let Err(error) = self.try_deliver(delivery).await {
+            println!("webhooks: {}", error);
+        }
+    }
+
+    async fn try_deliver(&self, delivery: WebhookDelivery) -> Result<(), mongodb::error::Error> {
+        let webhooks_store: Collection<Webhook> = self.queue.client.collection(WEBHOOKS_COLLECTION);
+        let Some(webhook) = webhooks_store
+            .find_one(doc! {"_id": delivery.webhook_id}, None)
+            .await?
+        else {
+            // The webhook was deleted after the delivery was queued.
+            self.deliveries_store()
+                .delete_one(doc! {"_id": delivery.id}, None)
+                .await?;
+            return Ok(());
+        };
+
+        let attempted_at = DateTime::now();
+        let started = Instant::now();
+        let response = if self.queue.allow_private_hosts || resolves_publicly(&webhook.url).await {
+            self.http
+                .post(&webhook.url)
+                .timeout(self.request_timeout)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, &delivery.event)
+                .header(DELIVERY_HEADER, delivery.id.to_string())
+                .header(
+                    SIGNATURE_HEADER,
+                    sign(&webhook.secret, delivery.payload.as_bytes()),
+                )
+                .body(delivery.payload.clone())
+                .send()
+                .await
+                .map_err(|error| error.to_string())
+        } else {
+            Err(String::from(
+                "the webhook host does not resolve to public addresses",
+            ))
+        };
+        let (status, error) = match response {
+            Ok(response) if response.status().is_success() => {
+                (Some(response.status().as_u16()), None)
+            }
+            Ok(response) => (
+                Some(response.status().as_u16()),
+                Some(format!("receiver answered {}", response.status())),
+            ),
+            Err(error) => (None, Some(error)),
+        };
+
+        let attempt = delivery.attempts + 1;
+        let logs_store: Collection<WebhookDeliveryLog> =
+            self.queue.client.collection(DELIVERY_LOGS_COLLECTION);
+        logs_store
+            .insert_one(
+                WebhookDeliveryLog {
+                    id: Uuid::new_v4(),
+                    delivery_id: delivery.id,
+                    webhook_id: delivery.webhook_id,
+                    attempt,
+                    status,
+                    error: error.clone(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    attempted_at,
+                },
+                None,
+            )
+            .await?;
+
+        match error {
+            None => {
+                self.deliveries_store()
+                    .delete_one(doc! {"_id": delivery.id}, None)
+                    .await?;
+            }
+            Some(error) if attempt >= self.max_attempts => {
+                let dead_letters_store: Collection<WebhookDelivery> =
+                    self.queue.client.collection(DEAD_LETTERS_COLLECTION);
+                // Upserted, so a move interrupted before the delete below is simply redone.
+                dead_letters_store
+                    .replace_one(
+                        doc! {"_id": delivery.id},
+                        WebhookDelivery {
+                            attempts: attempt,
+                            locked_until: None,
+                            last_error: Some(error),
+                            ..delivery.clone()
+                        },
+                        ReplaceOptions::builder().upsert(true).build(),
+                    )
+                    .await?;
+                self.deliveries_store()
+                    .delete_one(doc! {"_id": delivery.id}, None)
+                    .await?;
+            }
+            Some(error) => {
+                let next_attempt_at = DateTime::from_millis(
+                    DateTime::now().timestamp_millis() + self.backoff(attempt).as_millis() as i64,
+                );
+                self.deliveries_store()
+                    .update_one(
+                        doc! {"_id": delivery.id},
+                        doc! {
+                            "$set": {
+                                "attempts": attempt,
+                                "next_attempt_at": next_attempt_at,
+                                "last_error": error,
+                            },
+                            "$unset": {"locked_until": ""},
+                        },
+                        None,
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The wait after `attempt` failed: the initial backoff doubled for every earlier failure.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
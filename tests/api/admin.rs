@@ -0,0 +1,85 @@
+use std::process::{Command, Output};
+
+use crate::helpers::{dev, post_dev};
+use rinha_backend_2023_q3::admin;
+use rinha_backend_2023_q3::search_index::SearchIndex;
+
+/// Runs the server binary from the repository root, so it finds `configuration/`.
+fn run_cli(args: &[&str], envs: &[(&str, &str)]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rest-api-server"))
+        .args(args)
+        .envs(envs.iter().copied())
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("failed to run the cli")
+}
+
+#[test]
+fn prints_the_configuration_with_secrets_redacted() {
+    let output = run_cli(&["check-config"], &[]);
+
+    assert!(output.status.success());
+    let printed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(printed["database"]["password"], "[REDACTED]");
+    assert_eq!(printed["database"]["username"], "root");
+}
+
+#[test]
+fn fails_to_check_invalid_configurations() {
+    let output = run_cli(&["check-config"], &[("APP_APPLICATION_PORT", "not-a-port")]);
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn recognizes_test_database_names() {
+    let test_database_name = format!("test-{}", ulid::Ulid::new());
+
+    assert!(admin::is_test_database_name(&test_database_name));
+    for name in ["test", "test-", "test-foo", "admin", "rinha"] {
+        assert!(!admin::is_test_database_name(name), "{}", name);
+    }
+}
+
+#[tokio::test]
+async fn counts_and_gets_developers() {
+    let test_app = crate::helpers::spawn_app().await;
+    let created: serde_json::Value = post_dev(&test_app.address, &dev("foo"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let database = [(
+        "APP_DATABASE__DATABASE_NAME",
+        test_app.database_name.as_str(),
+    )];
+
+    let count = run_cli(&["count"], &database);
+    assert_eq!(String::from_utf8(count.stdout).unwrap().trim(), "1");
+
+    let found = run_cli(&["get", created["id"].as_str().unwrap()], &database);
+    let found: serde_json::Value = serde_json::from_slice(&found.stdout).unwrap();
+    assert_eq!(found["apelido"], "foo");
+
+    let missing = run_cli(&["get", &uuid::Uuid::new_v4().to_string()], &database);
+    assert!(!missing.status.success());
+}
+
+#[test]
+fn refuses_to_reindex_a_locked_search_index() {
+    let index_path = std::env::temp_dir().join(format!("search-index-{}", ulid::Ulid::new()));
+    let _server_index = SearchIndex::open(&index_path).unwrap();
+
+    let output = run_cli(
+        &["reindex"],
+        &[
+            ("APP_SEARCH_INDEX__PATH", index_path.to_str().unwrap()),
+            ("APP_SEARCH_INDEX__COMMIT_INTERVAL_MS", "1000"),
+        ],
+    );
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("search index is locked"));
+}
@@ -0,0 +1,205 @@
+use crate::helpers::{dev, post_dev};
+use reqwest::{header, StatusCode};
+use rinha_backend_2023_q3::configuration::BlobStoreConfiguration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::new_rgb8(width, height)
+        .write_to(&mut encoded, image::ImageFormat::Png)
+        .unwrap();
+    encoded.into_inner()
+}
+
+async fn put_avatar(address: &str, id: &str, image: Vec<u8>) -> reqwest::Response {
+    reqwest::Client::new()
+        .put(format!("{}/pessoas/{}/avatar", address, id))
+        .header(header::CONTENT_TYPE, "image/png")
+        .body(image)
+        .send()
+        .await
+        .expect("failed request")
+}
+
+async fn get_avatar(address: &str, id: &str, query: &str) -> reqwest::Response {
+    reqwest::get(format!("{}/pessoas/{}/avatar{}", address, id, query))
+        .await
+        .expect("failed request")
+}
+
+async fn create_dev(address: &str) -> String {
+    let created: serde_json::Value = post_dev(address, &dev("foo")).await.json().await.unwrap();
+    created["id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn stores_avatars_and_their_thumbnails() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = create_dev(&test_app.address).await;
+    let original = png(600, 300);
+
+    let stored = put_avatar(&test_app.address, &id, original.clone()).await;
+    assert_eq!(stored.status(), StatusCode::NO_CONTENT);
+    assert!(stored.headers().contains_key(header::ETAG));
+
+    let sent = get_avatar(&test_app.address, &id, "").await;
+    assert_eq!(sent.status(), StatusCode::OK);
+    assert_eq!(sent.headers()[header::CONTENT_TYPE], "image/png");
+    assert_eq!(
+        sent.headers()[header::CACHE_CONTROL],
+        "public, max-age=3600"
+    );
+    assert!(sent.headers().contains_key(header::LAST_MODIFIED));
+    assert_eq!(sent.bytes().await.unwrap(), original);
+
+    let thumbnail = get_avatar(&test_app.address, &id, "?size=64").await;
+    assert_eq!(thumbnail.status(), StatusCode::OK);
+    let thumbnail = image::load_from_memory(&thumbnail.bytes().await.unwrap()).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (64, 32));
+}
+
+#[tokio::test]
+async fn does_not_enlarge_small_avatars() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = create_dev(&test_app.address).await;
+    put_avatar(&test_app.address, &id, png(40, 20)).await;
+
+    let thumbnail = get_avatar(&test_app.address, &id, "?size=256").await;
+    let thumbnail = image::load_from_memory(&thumbnail.bytes().await.unwrap()).unwrap();
+
+    assert_eq!((thumbnail.width(), thumbnail.height()), (40, 20));
+}
+
+#[tokio::test]
+async fn revalidates_avatars_by_etag() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = create_dev(&test_app.address).await;
+    put_avatar(&test_app.address, &id, png(10, 10)).await;
+    let sent = get_avatar(&test_app.address, &id, "?size=64").await;
+    let etag = sent.headers()[header::ETAG].clone();
+
+    let revalidated = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas/{}/avatar?size=64",
+            test_app.address, id
+        ))
+        .header(header::IF_NONE_MATCH, etag.clone())
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(revalidated.status(), StatusCode::NOT_MODIFIED);
+
+    put_avatar(&test_app.address, &id, png(20, 10)).await;
+    let replaced = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas/{}/avatar?size=64",
+            test_app.address, id
+        ))
+        .header(header::IF_NONE_MATCH, etag)
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(replaced.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn stores_avatars_on_the_filesystem() {
+    let root = std::env::temp_dir().join(format!("avatars-{}", ulid::Ulid::new()));
+    let store_root = root.clone();
+    let test_app = crate::helpers::spawn_app_with(move |config| {
+        config.avatars.store = BlobStoreConfiguration::Filesystem { path: store_root };
+    })
+    .await;
+    let id = create_dev(&test_app.address).await;
+    let original = png(100, 100);
+
+    put_avatar(&test_app.address, &id, original.clone()).await;
+    let sent = get_avatar(&test_app.address, &id, "").await;
+
+    assert_eq!(sent.bytes().await.unwrap(), original);
+    assert!(root.join("avatars").join(&id).join("64").exists());
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+#[tokio::test]
+async fn rejects_avatars_that_are_not_images() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    // Sniffed before the person is looked up, so any id will do.
+    let response = put_avatar(
+        &test_app.address,
+        &uuid::Uuid::new_v4().to_string(),
+        b"GIF89a not really".to_vec(),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+}
+
+#[tokio::test]
+async fn rejects_avatars_over_the_size_limit() {
+    let test_app = crate::helpers::spawn_app_with(|config| config.avatars.max_bytes = 100).await;
+
+    let response = put_avatar(
+        &test_app.address,
+        &uuid::Uuid::new_v4().to_string(),
+        png(200, 200),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+#[tokio::test]
+async fn returns_400_for_truncated_uploads() {
+    let test_app = crate::helpers::spawn_app().await;
+    let mut stream = tokio::net::TcpStream::connect(test_app.address.trim_start_matches("http://"))
+        .await
+        .unwrap();
+
+    // Promises more bytes than it sends, well under the size limit.
+    let request = format!(
+        "PUT /pessoas/{}/avatar HTTP/1.1\r\nHost: localhost\r\nContent-Length: 100\r\n\r\n",
+        uuid::Uuid::new_v4()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    stream.write_all(&png(10, 10)[..10]).await.unwrap();
+    stream.shutdown().await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+}
+
+#[tokio::test]
+async fn returns_422_for_corrupt_images() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = create_dev(&test_app.address).await;
+    let mut corrupt = png(50, 50);
+    corrupt.truncate(40);
+
+    let response = put_avatar(&test_app.address, &id, corrupt).await;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn returns_404_for_missing_avatars() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = create_dev(&test_app.address).await;
+
+    let missing = get_avatar(&test_app.address, &id, "").await;
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    put_avatar(&test_app.address, &id, png(10, 10)).await;
+    let unknown_size = get_avatar(&test_app.address, &id, "?size=65").await;
+    assert_eq!(unknown_size.status(), StatusCode::NOT_FOUND);
+
+    let no_person = put_avatar(
+        &test_app.address,
+        &uuid::Uuid::new_v4().to_string(),
+        png(10, 10),
+    )
+    .await;
+    assert_eq!(no_person.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,118 @@
+use crate::helpers::{dev_with, post_dev};
+use chrono::{Datelike, Months, Utc};
+use reqwest::StatusCode;
+
+/// A person named `nickname` born on `birth_date`.
+fn born(nickname: &str, birth_date: impl Into<String>) -> serde_json::Value {
+    dev_with(
+        nickname,
+        serde_json::json!({"nascimento": birth_date.into()}),
+    )
+}
+
+/// The birth date of someone turning `age` today.
+fn born_years_ago(age: u32) -> String {
+    Utc::now()
+        .date_naive()
+        .checked_sub_months(Months::new(age * 12))
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn returns_422_for_birth_dates_in_the_future() {
+    let test_app = crate::helpers::spawn_app().await;
+    let next_year = format!("{}-01-01", Utc::now().year() + 1);
+
+    let response = post_dev(&test_app.address, &born("foo", next_year)).await;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn returns_422_for_ages_out_of_the_configured_range() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.birth_date.min_age_years = 16;
+        config.birth_date.max_age_years = 100;
+    })
+    .await;
+
+    for birth_date in ["0001-01-01", &born_years_ago(101), &born_years_ago(15)] {
+        let response = post_dev(&test_app.address, &born("foo", birth_date)).await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            birth_date
+        );
+    }
+}
+
+#[tokio::test]
+async fn reports_invalid_birth_dates_when_importing() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let report: serde_json::Value = reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", test_app.address))
+        .body(r#"{"apelido": "foo", "nome": "bar", "nascimento": "0001-01-01", "stack": null}"#)
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(report["lines"][0]["status"], 422);
+}
+
+#[tokio::test]
+async fn renders_the_age() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let created: serde_json::Value = post_dev(&test_app.address, &born("foo", born_years_ago(30)))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(created["idade"], 30);
+}
+
+#[tokio::test]
+async fn filters_by_age() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dev(&test_app.address, &born("young", born_years_ago(20))).await;
+    post_dev(&test_app.address, &born("middle", born_years_ago(35))).await;
+    post_dev(&test_app.address, &born("old", born_years_ago(60))).await;
+
+    let found: Vec<serde_json::Value> = reqwest::get(format!(
+        "{}/pessoas?min_age=35&max_age=59&sort=nickname",
+        test_app.address
+    ))
+    .await
+    .expect("failed request")
+    .json()
+    .await
+    .unwrap();
+
+    let nicknames: Vec<&str> = found
+        .iter()
+        .map(|dev| dev["apelido"].as_str().unwrap())
+        .collect();
+    assert_eq!(nicknames, vec!["middle"]);
+}
+
+#[tokio::test]
+async fn returns_400_for_empty_age_ranges() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::get(format!(
+        "{}/pessoas?min_age=40&max_age=30",
+        test_app.address
+    ))
+    .await
+    .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
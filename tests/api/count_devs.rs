@@ -1,3 +1,4 @@
+use crate::helpers::{dev, dev_with, post_dev};
 use reqwest::StatusCode;
 
 #[tokio::test]
@@ -17,24 +18,102 @@ async fn returns_200_ok_with_0_when_storage_is_empty() {
 #[tokio::test]
 async fn returns_200_ok_with_1_when_storage_got_one_dev() {
     let test_app = crate::helpers::spawn_app().await;
-    reqwest::Client::new()
-        .post(format!("{}/pessoas", test_app.address))
-        .json(&serde_json::json!({
-            "apelido": "foo",
-            "nome": "bar",
-            "nascimento": "2020-12-03",
-            "stack": ["Rust", "Python"]
-        }))
+    post_dev(&test_app.address, &dev("foo")).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/contagem-pessoas", &test_app.address))
         .send()
         .await
         .expect("failed request");
 
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.text().await.unwrap(), "1");
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_grouped_counts_when_accepting_json() {
+    let test_app = crate::helpers::spawn_app().await;
+    for (nickname, birth_date, stacks) in [
+        ("foo", "2020-12-03", serde_json::json!(["Rust", "Python"])),
+        ("baz", "1992-11-23", serde_json::json!(["Rust"])),
+        ("qux", "1992-01-01", serde_json::Value::Null),
+    ] {
+        post_dev(
+            &test_app.address,
+            &dev_with(
+                nickname,
+                serde_json::json!({"nascimento": birth_date, "stack": stacks}),
+            ),
+        )
+        .await;
+    }
+
     let response = reqwest::Client::new()
         .get(format!("{}/contagem-pessoas", &test_app.address))
+        .header("Accept", "application/json")
         .send()
         .await
         .expect("failed request");
 
     assert_eq!(response.status(), StatusCode::OK);
-    assert_eq!(response.text().await.unwrap(), "1");
+    let response_body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(response_body["total"], 3);
+    assert_eq!(
+        response_body["by_stack"],
+        serde_json::json!({"Python": 1, "Rust": 2})
+    );
+    assert_eq!(
+        response_body["by_birth_year"],
+        serde_json::json!({"1992": 2, "2020": 1})
+    );
+    let by_creation_day = response_body["by_creation_day"]
+        .as_object()
+        .expect("counts by creation day");
+    assert_eq!(
+        by_creation_day
+            .values()
+            .map(|count| count.as_u64().unwrap())
+            .sum::<u64>(),
+        3
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_plain_text_unless_json_is_preferred() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dev(&test_app.address, &dev("foo")).await;
+
+    for accept in [
+        "application/json, text/plain, */*",
+        "*/*",
+        "application/json;q=0",
+        "text/plain;q=0.9, application/json;q=0.5",
+    ] {
+        let response = reqwest::Client::new()
+            .get(format!("{}/contagem-pessoas", &test_app.address))
+            .header("Accept", accept)
+            .send()
+            .await
+            .expect("failed request");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "1", "{}", accept);
+    }
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_zeroed_counts_when_accepting_json_and_storage_is_empty() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/contagem-pessoas", &test_app.address))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(response_body["total"], 0);
+    assert_eq!(response_body["by_stack"], serde_json::json!({}));
 }
@@ -0,0 +1,144 @@
+use crate::helpers::{dev, post_dev};
+use reqwest::StatusCode;
+use std::time::Duration;
+
+async fn post_and_delete_dev(address: &str, nickname: &str) -> String {
+    let created: serde_json::Value = post_dev(address, &dev(nickname))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let id = created["id"].as_str().unwrap().to_string();
+    let response = reqwest::Client::new()
+        .delete(format!("{}/pessoas/{}", address, id))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    id
+}
+
+#[tokio::test]
+async fn hides_deleted_devs() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = post_and_delete_dev(&test_app.address, "foo").await;
+    post_dev(&test_app.address, &dev("fool")).await;
+
+    let found = reqwest::get(format!("{}/pessoas/{}", &test_app.address, id))
+        .await
+        .expect("failed request");
+    assert_eq!(found.status(), StatusCode::NOT_FOUND);
+
+    let searched: Vec<serde_json::Value> =
+        reqwest::get(format!("{}/pessoas?t=foo", &test_app.address))
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(searched.len(), 1);
+    assert_eq!(searched[0]["apelido"], "fool");
+
+    let count = reqwest::get(format!("{}/contagem-pessoas", &test_app.address))
+        .await
+        .expect("failed request")
+        .text()
+        .await
+        .unwrap();
+    assert_eq!(count, "1");
+}
+
+#[tokio::test]
+async fn returns_404_not_found_when_deleting_twice() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = post_and_delete_dev(&test_app.address, "foo").await;
+
+    let response = reqwest::Client::new()
+        .delete(format!("{}/pessoas/{}", &test_app.address, id))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn restores_deleted_devs() {
+    let test_app = crate::helpers::spawn_app().await;
+    let id = post_and_delete_dev(&test_app.address, "foo").await;
+
+    let restored = reqwest::Client::new()
+        .post(format!("{}/pessoas/{}/restore", &test_app.address, id))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(restored.status(), StatusCode::OK);
+    assert_eq!(
+        restored.json::<serde_json::Value>().await.unwrap()["apelido"],
+        "foo"
+    );
+
+    let found = reqwest::get(format!("{}/pessoas/{}", &test_app.address, id))
+        .await
+        .expect("failed request");
+    assert_eq!(found.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn returns_404_not_found_when_restoring_a_dev_that_was_not_deleted() {
+    let test_app = crate::helpers::spawn_app().await;
+    let created: serde_json::Value = post_dev(&test_app.address, &dev("foo"))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/pessoas/{}/restore",
+            &test_app.address,
+            created["id"].as_str().unwrap()
+        ))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn keeps_the_nickname_of_deleted_devs_reserved() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_and_delete_dev(&test_app.address, "foo").await;
+
+    let response = post_dev(&test_app.address, &dev("foo")).await;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn purges_devs_after_the_retention() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.soft_delete.retention_seconds = 0;
+        config.soft_delete.purge_interval_ms = 50;
+    })
+    .await;
+    let id = post_and_delete_dev(&test_app.address, "foo").await;
+
+    let mut recreated = StatusCode::UNPROCESSABLE_ENTITY;
+    for _ in 0..50 {
+        recreated = post_dev(&test_app.address, &dev("foo")).await.status();
+        if recreated == StatusCode::CREATED {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(recreated, StatusCode::CREATED);
+
+    let restored = reqwest::Client::new()
+        .post(format!("{}/pessoas/{}/restore", &test_app.address, id))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(restored.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,84 @@
+use crate::helpers::{dev_with, post_dev};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn returns_200_ok_with_ndjson_by_default() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with("foo", serde_json::json!({"stack": ["Rust", "Python"]})),
+    )
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with("baz", serde_json::json!({"stack": ["Rust", "Python"]})),
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas/export", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(CONTENT_TYPE).unwrap(),
+        "application/x-ndjson"
+    );
+    let response_body = response.text().await.unwrap();
+    let lines = response_body
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("a json line"))
+        .collect::<Vec<serde_json::Value>>();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["nome"], String::from("bar"));
+    assert_eq!(
+        lines[0]["stack"],
+        serde_json::json!([String::from("Rust"), String::from("Python")])
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_csv_given_csv_format() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with("foo", serde_json::json!({"stack": ["Rust", "Python"]})),
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas/export?format=csv", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/csv");
+    let response_body = response.text().await.unwrap();
+    let mut lines = response_body.lines();
+    assert_eq!(lines.next(), Some("id,apelido,nome,nascimento,stack"));
+    assert!(lines
+        .next()
+        .expect("a csv row")
+        .ends_with(",foo,bar,2020-12-03,Rust;Python"));
+    assert_eq!(lines.next(), None);
+}
+
+#[tokio::test]
+async fn returns_400_bad_request_given_parquet_format() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas/export?format=parquet",
+            &test_app.address
+        ))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
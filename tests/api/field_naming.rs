@@ -0,0 +1,118 @@
+use reqwest::header::{ACCEPT, CONTENT_TYPE, LOCATION};
+use reqwest::StatusCode;
+
+const ENGLISH: &str = "application/vnd.rinha.en+json";
+
+#[tokio::test]
+async fn accepts_english_field_names() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let post_response = client
+        .post(format!("{}/pessoas", test_app.address))
+        .json(&serde_json::json!({
+            "nickname": "foo",
+            "name": "bar",
+            "birth_date": "2020-12-03",
+            "stacks": ["Rust"]
+        }))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(post_response.status(), StatusCode::CREATED);
+    assert_eq!(post_response.headers()[CONTENT_TYPE], "application/json");
+    let created: serde_json::Value = post_response.json().await.unwrap();
+    assert_eq!(created["apelido"], "foo");
+    assert_eq!(created["nome"], "bar");
+    assert_eq!(created["nascimento"], "2020-12-03");
+    assert_eq!(created["stack"], serde_json::json!(["Rust"]));
+}
+
+#[tokio::test]
+async fn renders_english_field_names_when_accepted() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let post_response = client
+        .post(format!("{}/pessoas", test_app.address))
+        .header(ACCEPT, ENGLISH)
+        .json(&serde_json::json!({
+            "apelido": "foo",
+            "nome": "bar",
+            "nascimento": "2020-12-03",
+            "stack": ["Rust"]
+        }))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(post_response.status(), StatusCode::CREATED);
+    assert_eq!(post_response.headers()[CONTENT_TYPE], ENGLISH);
+    let location = post_response.headers()[LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let created: serde_json::Value = post_response.json().await.unwrap();
+    assert_eq!(created["nickname"], "foo");
+    assert_eq!(created["name"], "bar");
+    assert_eq!(created["birth_date"], "2020-12-03");
+    assert_eq!(created["stacks"], serde_json::json!(["Rust"]));
+    assert!(created.get("apelido").is_none());
+
+    let get_response = client
+        .get(format!("{}{}", test_app.address, location))
+        .header(ACCEPT, ENGLISH)
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(get_response.headers()[CONTENT_TYPE], ENGLISH);
+    let found: serde_json::Value = get_response.json().await.unwrap();
+    assert_eq!(found, created);
+
+    let search_response = client
+        .get(format!("{}/pessoas?t=foo", test_app.address))
+        .header(ACCEPT, ENGLISH)
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(search_response.headers()[CONTENT_TYPE], ENGLISH);
+    let found: serde_json::Value = search_response.json().await.unwrap();
+    assert_eq!(found, serde_json::json!([created]));
+}
+
+#[tokio::test]
+async fn replays_in_the_naming_of_the_retry() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+    let body = serde_json::json!({
+        "apelido": "foo",
+        "nome": "bar",
+        "nascimento": "2020-12-03",
+        "stack": null
+    });
+
+    let first: serde_json::Value = client
+        .post(format!("{}/pessoas", test_app.address))
+        .header("idempotency-key", "retried")
+        .json(&body)
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    let retry = client
+        .post(format!("{}/pessoas", test_app.address))
+        .header("idempotency-key", "retried")
+        .header(ACCEPT, ENGLISH)
+        .json(&body)
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert_eq!(retry.headers()["idempotent-replayed"], "true");
+    let replayed: serde_json::Value = retry.json().await.unwrap();
+    assert_eq!(replayed["id"], first["id"]);
+    assert_eq!(replayed["nickname"], "foo");
+}
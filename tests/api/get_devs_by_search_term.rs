@@ -1,3 +1,4 @@
+use crate::helpers::{dev_with, post_dev};
 use reqwest::StatusCode;
 
 #[tokio::test]
@@ -139,3 +140,387 @@ async fn returns_200_ok_with_dev_when_searching_by_stack_without_exact_match() {
         response_body.pop().expect("a person in response");
     assert_eq!(only_response["id"], post_response_body["id"])
 }
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_when_searching_by_stack_alias() {
+    let test_app = crate::helpers::spawn_app().await;
+    let post_response = reqwest::Client::new()
+        .post(format!("{}/pessoas", test_app.address))
+        .json(&serde_json::json!({
+            "apelido": "foo",
+            "nome": "bar",
+            "nascimento": "2020-12-03",
+            "stack": [" Node.js "]
+        }))
+        .send()
+        .await
+        .expect("failed request");
+    let post_response_body = post_response
+        .json::<std::collections::HashMap<String, serde_json::Value>>()
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?t=NodeJS", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut response_body = response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap();
+    let only_response: std::collections::HashMap<String, serde_json::Value> =
+        response_body.pop().expect("a person in response");
+    assert_eq!(only_response["id"], post_response_body["id"]);
+    assert_eq!(
+        only_response["stack"],
+        serde_json::json!([String::from(" Node.js ")])
+    );
+}
+
+async fn post_dev_and_search(
+    nickname: &str,
+    name: &str,
+    search_term: &str,
+) -> (
+    serde_json::Value,
+    Vec<std::collections::HashMap<String, serde_json::Value>>,
+) {
+    let test_app = crate::helpers::spawn_app().await;
+    let post_response = post_dev(
+        &test_app.address,
+        &dev_with(
+            nickname,
+            serde_json::json!({"nome": name, "nascimento": "1992-11-23"}),
+        ),
+    )
+    .await;
+    let post_response_body = post_response
+        .json::<std::collections::HashMap<String, serde_json::Value>>()
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas", &test_app.address))
+        .query(&[("t", search_term)])
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap();
+    (post_response_body["id"].clone(), response_body)
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_when_searching_name_without_accents() {
+    let (id, mut response_body) = post_dev_and_search("jj", "João da Silva", "joao").await;
+
+    let only_response = response_body.pop().expect("a person in response");
+    assert_eq!(only_response["id"], id);
+    assert_eq!(only_response["nome"], String::from("João da Silva"));
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_when_searching_uppercase_name_without_cedilla() {
+    let (id, mut response_body) =
+        post_dev_and_search("ceicao", "Maria da Conceição", "CONCEICAO").await;
+
+    let only_response = response_body.pop().expect("a person in response");
+    assert_eq!(only_response["id"], id);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_when_searching_accented_term_for_unaccented_nickname() {
+    let (id, mut response_body) = post_dev_and_search("jose", "Zé Pequeno", "José").await;
+
+    let only_response = response_body.pop().expect("a person in response");
+    assert_eq!(only_response["id"], id);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_when_searching_with_decomposed_accents() {
+    let (id, mut response_body) =
+        post_dev_and_search("avila", "Ávila Gonçalves", "a\u{301}vila").await;
+
+    let only_response = response_body.pop().expect("a person in response");
+    assert_eq!(only_response["id"], id);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_empty_list_when_folded_term_does_not_match() {
+    let (_, response_body) = post_dev_and_search("jj", "João da Silva", "joana").await;
+
+    assert!(response_body.is_empty());
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_empty_list_when_term_looks_like_a_regex() {
+    let (_, wildcard) = post_dev_and_search("jj", "Joao da Silva", "jo.o").await;
+    let (_, unbalanced) = post_dev_and_search("jj", "Joao da Silva", "(").await;
+
+    assert!(wildcard.is_empty());
+    assert!(unbalanced.is_empty());
+}
+
+async fn post_devs(address: &str, devs: &[(&str, &str, &[&str])]) {
+    for (nickname, name, stacks) in devs {
+        post_dev(
+            address,
+            &dev_with(
+                nickname,
+                serde_json::json!({"nome": name, "nascimento": "1992-11-23", "stack": stacks}),
+            ),
+        )
+        .await;
+    }
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_devs_ranked_by_relevance() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(
+        &test_app.address,
+        &[
+            ("caio", "Caio", &["Rust"]),
+            ("rustacean", "Ana", &[]),
+            ("dan", "Dan Rust", &[]),
+            ("rust", "Bia", &[]),
+        ],
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?t=rust", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap();
+    let nicknames = response_body
+        .iter()
+        .map(|dev| dev["apelido"].as_str().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(nicknames, vec!["rust", "rustacean", "dan", "caio"]);
+    assert!(response_body.iter().all(|dev| !dev.contains_key("score")));
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_scores_when_explaining() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(
+        &test_app.address,
+        &[("rust", "Bia", &[]), ("caio", "Caio", &["Rust"])],
+    )
+    .await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?t=rust&explain=true", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap();
+    let scores = response_body
+        .iter()
+        .map(|dev| dev["score"].as_f64().expect("a score"))
+        .collect::<Vec<f64>>();
+    assert_eq!(scores.len(), 2);
+    assert!(scores[0] > scores[1]);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_when_searching_with_a_typo_and_fuzzy() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(&test_app.address, &[("foo", "bar", &["Python"])]).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?t=pyhton&fuzzy=true", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap();
+    assert_eq!(response_body.len(), 1);
+    assert_eq!(response_body[0]["apelido"], String::from("foo"));
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_empty_list_when_searching_with_a_typo_without_fuzzy() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(&test_app.address, &[("foo", "bar", &["Python"])]).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?t=pyhton", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap();
+    assert!(response_body.is_empty());
+}
+
+async fn search_nicknames(address: &str, query: &str) -> Vec<String> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?{}", address, query))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap()
+        .iter()
+        .map(|dev| dev["apelido"].as_str().unwrap().to_string())
+        .collect()
+}
+
+async fn post_dated_devs(address: &str) {
+    for (nickname, birth_date, stacks) in [
+        ("ana", "1985-03-01", serde_json::json!(["Rust", "Go"])),
+        ("bia", "1993-07-12", serde_json::json!(["Rust"])),
+        ("caio", "1999-01-30", serde_json::json!(["Python"])),
+        ("dan", "2001-10-05", serde_json::json!(["NodeJS", "Rust"])),
+    ] {
+        reqwest::Client::new()
+            .post(format!("{}/pessoas", address))
+            .json(&serde_json::json!({
+                "apelido": nickname,
+                "nome": "Fulano",
+                "nascimento": birth_date,
+                "stack": stacks
+            }))
+            .send()
+            .await
+            .expect("failed request");
+    }
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_devs_matching_stack_and_birth_date_filters() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dated_devs(&test_app.address).await;
+
+    let nicknames = search_nicknames(
+        &test_app.address,
+        "stack=rust&born_after=1990-01-01&sort=birth_date",
+    )
+    .await;
+
+    assert_eq!(nicknames, vec!["bia", "dan"]);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_devs_matching_term_and_stack_alias() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dated_devs(&test_app.address).await;
+
+    let nicknames = search_nicknames(&test_app.address, "t=fulano&stack=node&sort=-nickname").await;
+
+    assert_eq!(nicknames, vec!["dan"]);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_dev_matching_nickname_exactly() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dated_devs(&test_app.address).await;
+
+    let nicknames = search_nicknames(&test_app.address, "nickname=BIA").await;
+
+    assert_eq!(nicknames, vec!["bia"]);
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_devs_born_in_range_sorted_descending() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dated_devs(&test_app.address).await;
+
+    let nicknames = search_nicknames(
+        &test_app.address,
+        "born_after=1985-03-01&born_before=2001-10-05&sort=-birth_date",
+    )
+    .await;
+
+    assert_eq!(nicknames, vec!["caio", "bia"]);
+}
+
+#[tokio::test]
+async fn returns_400_bad_request_without_any_criteria() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn returns_400_bad_request_given_invalid_birth_date_filter() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas?born_after=1990-13-01",
+            &test_app.address
+        ))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn returns_400_bad_request_given_empty_birth_date_range() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas?born_after=2000-01-01&born_before=1990-01-01",
+            &test_app.address
+        ))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn returns_400_bad_request_given_unknown_sort() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?t=foo&sort=age", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -2,6 +2,7 @@ use std::sync::Once;
 
 use tracing_subscriber::EnvFilter;
 
+use rinha_backend_2023_q3::configuration::StaticConfiguration;
 use rinha_backend_2023_q3::startup::Application;
 use rinha_backend_2023_q3::{configuration, telemetry};
 
@@ -9,9 +10,14 @@ static TRACING: Once = Once::new();
 
 pub struct TestApp {
     pub address: String,
+    pub database_name: String,
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+pub async fn spawn_app_with(configure: impl FnOnce(&mut StaticConfiguration)) -> TestApp {
     TRACING.call_once(|| {
         let default_filter_level = EnvFilter::new("info");
         let subscriber_name = "rinha-de-backend-2023-q3";
@@ -30,11 +36,55 @@ pub async fn spawn_app() -> TestApp {
     let mut static_config =
         configuration::get_static_configuration().expect("failed to load configs");
     let test_database_name = format!("test-{}", &ulid::Ulid::new().to_string());
-    static_config.database.database_name = test_database_name;
+    static_config.database.database_name = test_database_name.clone();
+    configure(&mut static_config);
 
     let application = Application::build(static_config).await;
     let address = format!("http://{}", application.address());
 
     tokio::spawn(async move { application.run().await.expect("Failed to run the server") });
-    TestApp { address }
+    TestApp {
+        address,
+        database_name: test_database_name,
+    }
+}
+
+/// A valid person named `nickname`.
+pub fn dev(nickname: &str) -> serde_json::Value {
+    dev_with(nickname, serde_json::json!({}))
+}
+
+/// [`dev`] with `fields` replacing or adding to the defaults.
+pub fn dev_with(nickname: &str, fields: serde_json::Value) -> serde_json::Value {
+    let mut dev = serde_json::json!({
+        "apelido": nickname,
+        "nome": "bar",
+        "nascimento": "2020-12-03",
+        "stack": ["Rust"]
+    });
+    dev.as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    dev
+}
+
+pub async fn post_dev(address: &str, dev: &serde_json::Value) -> reqwest::Response {
+    post_dev_with_headers(address, dev, &[]).await
+}
+
+pub async fn post_dev_with_headers(
+    address: &str,
+    dev: &serde_json::Value,
+    headers: &[(&str, &str)],
+) -> reqwest::Response {
+    headers
+        .iter()
+        .fold(
+            reqwest::Client::new().post(format!("{}/pessoas", address)),
+            |request, (name, value)| request.header(*name, *value),
+        )
+        .json(dev)
+        .send()
+        .await
+        .expect("failed request")
 }
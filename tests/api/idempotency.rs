@@ -0,0 +1,102 @@
+use crate::helpers::{dev, post_dev_with_headers};
+use reqwest::header::LOCATION;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn replays_the_original_response_on_retries() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let original = post_dev_with_headers(
+        &test_app.address,
+        &dev("foo"),
+        &[("Idempotency-Key", "retry-me")],
+    )
+    .await;
+    let retried = post_dev_with_headers(
+        &test_app.address,
+        &dev("foo"),
+        &[("Idempotency-Key", "retry-me")],
+    )
+    .await;
+
+    assert_eq!(original.status(), StatusCode::CREATED);
+    assert_eq!(retried.status(), StatusCode::CREATED);
+    assert_eq!(
+        original.headers().get(LOCATION),
+        retried.headers().get(LOCATION)
+    );
+    assert!(original.headers().get("Idempotent-Replayed").is_none());
+    assert_eq!(
+        retried.headers().get("Idempotent-Replayed").unwrap(),
+        "true"
+    );
+    assert_eq!(
+        original.json::<serde_json::Value>().await.unwrap(),
+        retried.json::<serde_json::Value>().await.unwrap()
+    );
+
+    let found: Vec<serde_json::Value> =
+        reqwest::get(format!("{}/pessoas?t=foo", &test_app.address))
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(found.len(), 1);
+}
+
+#[tokio::test]
+async fn rejects_a_key_reused_with_another_body() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    post_dev_with_headers(
+        &test_app.address,
+        &dev("foo"),
+        &[("Idempotency-Key", "reused")],
+    )
+    .await;
+    let response = post_dev_with_headers(
+        &test_app.address,
+        &dev("bar"),
+        &[("Idempotency-Key", "reused")],
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn keeps_keys_apart() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let first = post_dev_with_headers(
+        &test_app.address,
+        &dev("foo"),
+        &[("Idempotency-Key", "first")],
+    )
+    .await;
+    let second = post_dev_with_headers(
+        &test_app.address,
+        &dev("bar"),
+        &[("Idempotency-Key", "second")],
+    )
+    .await;
+
+    assert_eq!(second.status(), StatusCode::CREATED);
+    assert_ne!(
+        first.headers().get(LOCATION),
+        second.headers().get(LOCATION)
+    );
+}
+
+#[tokio::test]
+async fn rejects_invalid_keys() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    for key in ["", "with space", &"k".repeat(256)] {
+        let response =
+            post_dev_with_headers(&test_app.address, &dev("foo"), &[("Idempotency-Key", key)])
+                .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{:?}", key);
+    }
+}
@@ -0,0 +1,93 @@
+use reqwest::StatusCode;
+use rinha_backend_2023_q3::import;
+
+#[tokio::test]
+async fn returns_200_ok_with_report_given_valid_and_invalid_lines() {
+    let test_app = crate::helpers::spawn_app().await;
+    let body = [
+        r#"{"apelido": "foo", "nome": "bar", "nascimento": "2020-12-03", "stack": ["Rust"]}"#,
+        "",
+        r#"{"apelido": "baz", "nascimento": "2020-12-03"}"#,
+        r#"{"apelido": "qux", "nome": "quux", "nascimento": "1992-11-23", "stack": null}"#,
+    ]
+    .join("\n");
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", test_app.address))
+        .header("Content-Type", "application/x-ndjson")
+        .body(body)
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(response_body["inserted"], 2);
+    assert_eq!(response_body["failed"], 1);
+    let lines = response_body["lines"].as_array().expect("lines in report");
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["line"], 1);
+    assert_eq!(lines[0]["status"], 201);
+    assert_eq!(lines[1]["line"], 3);
+    assert_eq!(lines[1]["status"], 422);
+    assert_eq!(lines[2]["line"], 4);
+    assert_eq!(lines[2]["status"], 201);
+}
+
+#[tokio::test]
+async fn reports_oversized_lines_without_failing_the_import() {
+    let test_app = crate::helpers::spawn_app().await;
+    let body = [
+        format!(r#"{{"apelido": "{}"}}"#, "a".repeat(import::MAX_LINE_BYTES)),
+        String::from(r#"{"apelido": "baz"}"#),
+    ]
+    .join("\n");
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", test_app.address))
+        .header("Content-Type", "application/x-ndjson")
+        .body(body)
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(response_body["failed"], 2);
+    let lines = response_body["lines"].as_array().expect("lines in report");
+    assert_eq!(lines[0]["line"], 1);
+    assert_eq!(lines[0]["status"], 413);
+    assert_eq!(lines[1]["line"], 2);
+    assert_eq!(lines[1]["status"], 422);
+}
+
+#[tokio::test]
+async fn stores_imported_devs() {
+    let test_app = crate::helpers::spawn_app().await;
+    let body = (0..5)
+        .map(|index| {
+            serde_json::json!({
+                "apelido": format!("foo-{}", index),
+                "nome": "bar",
+                "nascimento": "2020-12-03",
+                "stack": ["Rust", "Python"]
+            })
+            .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", test_app.address))
+        .body(body)
+        .send()
+        .await
+        .expect("failed request");
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/contagem-pessoas", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.text().await.unwrap(), "5");
+}
@@ -0,0 +1,108 @@
+use crate::helpers::{dev_with, post_dev};
+use futures::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+async fn connect(address: &str) -> Socket {
+    let url = format!("{}/pessoas/live", address.replacen("http", "ws", 1));
+    connect_async(url).await.expect("failed to connect").0
+}
+
+async fn send(socket: &mut Socket, message: serde_json::Value) {
+    socket
+        .send(Message::Text(message.to_string()))
+        .await
+        .expect("failed to send");
+}
+
+async fn receive(socket: &mut Socket) -> serde_json::Value {
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("no message received")
+        .expect("connection closed")
+        .expect("failed to receive");
+    serde_json::from_str(message.to_text().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn sends_current_results_then_new_matches() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with("rustacean", serde_json::json!({"stack": &["Rust"]})),
+    )
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with("gopher", serde_json::json!({"stack": &["Go"]})),
+    )
+    .await;
+    let mut socket = connect(&test_app.address).await;
+
+    send(
+        &mut socket,
+        serde_json::json!({"action": "subscribe", "term": "rust"}),
+    )
+    .await;
+    let results = receive(&mut socket).await;
+    assert_eq!(results["type"], "results");
+    assert_eq!(results["term"], "rust");
+    assert_eq!(results["persons"].as_array().unwrap().len(), 1);
+    assert_eq!(results["persons"][0]["apelido"], "rustacean");
+
+    post_dev(
+        &test_app.address,
+        &dev_with("gopher2", serde_json::json!({"stack": &["Go"]})),
+    )
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with("ferris", serde_json::json!({"stack": &["Rust"]})),
+    )
+    .await;
+    let matched = receive(&mut socket).await;
+    assert_eq!(matched["type"], "match");
+    assert_eq!(matched["term"], "rust");
+    assert_eq!(matched["person"]["apelido"], "ferris");
+}
+
+#[tokio::test]
+async fn rejects_subscriptions_over_the_limit() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.live_search.max_subscriptions = 1;
+    })
+    .await;
+    let mut socket = connect(&test_app.address).await;
+
+    send(
+        &mut socket,
+        serde_json::json!({"action": "subscribe", "term": "rust"}),
+    )
+    .await;
+    assert_eq!(receive(&mut socket).await["type"], "results");
+    send(
+        &mut socket,
+        serde_json::json!({"action": "subscribe", "term": "go"}),
+    )
+    .await;
+    assert_eq!(receive(&mut socket).await["type"], "error");
+}
+
+#[tokio::test]
+async fn rejects_connections_over_the_limit() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.live_search.max_connections = 1;
+    })
+    .await;
+    let _socket = connect(&test_app.address).await;
+
+    let url = format!(
+        "{}/pessoas/live",
+        test_app.address.replacen("http", "ws", 1)
+    );
+    let rejected = connect_async(url).await;
+    assert!(rejected.is_err());
+}
@@ -1,8 +1,26 @@
+mod admin;
+mod avatars;
+mod birth_date;
+mod count_devs;
+mod delete_devs;
+mod export_devs;
+mod field_naming;
 mod get_dev_by_id;
+mod get_devs_by_search_term;
 mod health_check;
 pub mod helpers;
+mod idempotency;
+mod import_devs;
+mod live_search;
+mod migrations;
+mod openapi;
+mod outbox;
+mod person_stream;
 mod post_devs;
-
-mod count_devs;
-
-mod get_devs_by_search_term;
+mod profile;
+mod search_index;
+mod stack_entries;
+mod stacks;
+mod tenancy;
+mod versioning;
+mod webhooks;
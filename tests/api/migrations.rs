@@ -0,0 +1,131 @@
+use mongodb::bson::{doc, Document};
+use uuid::Uuid;
+
+use rinha_backend_2023_q3::configuration;
+use rinha_backend_2023_q3::migrations::{self, CURRENT_SCHEMA_VERSION};
+use rinha_backend_2023_q3::normalization::StackAliases;
+use rinha_backend_2023_q3::startup::get_database_connection;
+use rinha_backend_2023_q3::storage::PersonStore;
+
+/// A person as stored before schema versions, stack aliases, folded search terms and stack
+/// entries.
+fn legacy_person(id: Uuid) -> Document {
+    doc! {
+        "_id": id,
+        "nickname": "joão",
+        "name": "João Silva",
+        "birth_date": "1990-01-01",
+        "stacks": ["rust", "node"],
+    }
+}
+
+fn stack_aliases() -> StackAliases {
+    StackAliases::new(
+        &configuration::get_static_configuration()
+            .unwrap()
+            .stack_aliases,
+    )
+}
+
+#[test]
+fn upgrades_unversioned_documents_on_read() {
+    let id = Uuid::new_v4();
+
+    let dev = migrations::read_person(legacy_person(id), &stack_aliases()).unwrap();
+
+    assert_eq!(dev.id, id);
+    assert_eq!(dev.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(
+        dev.search_terms,
+        Some(vec![
+            String::from("joao"),
+            String::from("joao silva"),
+            String::from("rust"),
+            String::from("node.js"),
+            String::from("rust"),
+            String::from("node"),
+        ])
+    );
+    let entries = dev.stack_entries.unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        (entries[1].stack.as_str(), entries[1].label.as_str()),
+        ("node.js", "node")
+    );
+}
+
+#[test]
+fn canonicalizes_the_stacks_of_documents_predating_aliases() {
+    let mut stored = legacy_person(Uuid::new_v4());
+
+    assert!(migrations::upgrade(&mut stored, &stack_aliases()));
+
+    assert_eq!(
+        stored.get_array("stacks").unwrap(),
+        &vec!["rust".into(), "node.js".into()]
+    );
+    assert_eq!(
+        stored.get_array("stack_labels").unwrap(),
+        &vec!["rust".into(), "node".into()]
+    );
+}
+
+#[test]
+fn leaves_current_documents_alone() {
+    let mut stored = legacy_person(Uuid::new_v4());
+    assert!(migrations::upgrade(&mut stored, &stack_aliases()));
+    let upgraded = stored.clone();
+
+    assert!(!migrations::upgrade(&mut stored, &stack_aliases()));
+    assert_eq!(stored, upgraded);
+    assert_eq!(migrations::schema_version(&stored), CURRENT_SCHEMA_VERSION);
+}
+
+#[tokio::test]
+async fn stores_upgraded_documents_when_read() {
+    let test_app = crate::helpers::spawn_app().await;
+    let mut static_config = configuration::get_static_configuration().unwrap();
+    static_config.database.database_name = test_app.database_name.clone();
+    let client = PersonStore::new(
+        get_database_connection(static_config.database.clone())
+            .await
+            .expect("failed to connect to mongodb"),
+        static_config.database.collection_name,
+        std::sync::Arc::new(StackAliases::new(&static_config.stack_aliases)),
+    );
+    let id = Uuid::new_v4();
+    client
+        .stored_persons()
+        .insert_one(legacy_person(id), None)
+        .await
+        .unwrap();
+
+    let found: serde_json::Value = reqwest::get(format!("{}/pessoas/{}", test_app.address, id))
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+
+    assert_eq!(found["apelido"], "joão");
+    assert_eq!(found["stack"], serde_json::json!(["rust", "node"]));
+    let stored = client
+        .stored_persons()
+        .find_one(doc! {"nickname": "joão"}, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(migrations::schema_version(&stored), CURRENT_SCHEMA_VERSION);
+    assert_eq!(
+        stored.get_array("stacks").unwrap(),
+        &vec!["rust".into(), "node.js".into()]
+    );
+    let by_alias: Vec<serde_json::Value> =
+        reqwest::get(format!("{}/pessoas?t=nodejs", test_app.address))
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(by_alias.len(), 1);
+}
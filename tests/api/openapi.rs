@@ -0,0 +1,118 @@
+use reqwest::StatusCode;
+use rinha_backend_2023_q3::startup;
+use rinha_backend_2023_q3::structs::api;
+use std::collections::BTreeSet;
+use uuid::Uuid;
+
+/// The `(method, path)` of every documented route in the router's table, with path parameters
+/// written the OpenAPI way.
+fn registered_routes() -> BTreeSet<(String, String)> {
+    startup::documented_routes()
+        .into_iter()
+        .map(|(method, path)| {
+            let path = path
+                .split('/')
+                .map(|segment| match segment.strip_prefix(':') {
+                    Some(parameter) => format!("{{{}}}", parameter),
+                    None => segment.to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join("/");
+            (method.as_str().to_lowercase(), path)
+        })
+        .collect()
+}
+
+async fn fetch_spec(address: &str) -> serde_json::Value {
+    let response = reqwest::Client::new()
+        .get(format!("{}/openapi.json", address))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn documents_every_registered_route() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let spec = fetch_spec(&test_app.address).await;
+
+    let documented: BTreeSet<(String, String)> = spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, operations)| {
+            operations
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (method.clone(), path.clone()))
+        })
+        .collect();
+    let registered = registered_routes();
+    assert!(!registered.is_empty());
+    assert_eq!(
+        registered.difference(&documented).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "routes missing from the OpenAPI document"
+    );
+    assert_eq!(
+        documented.difference(&registered).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "documented routes that are not registered"
+    );
+}
+
+#[tokio::test]
+async fn documents_the_person_schema() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let spec = fetch_spec(&test_app.address).await;
+
+    let person = &spec["components"]["schemas"]["PersonBody"]["properties"];
+    for field in ["id", "apelido", "nome", "nascimento", "stack", "idade"] {
+        assert!(person.get(field).is_some(), "missing {}", field);
+    }
+    assert_eq!(person["id"]["type"], "string");
+    assert_eq!(person["id"]["format"], "uuid");
+}
+
+#[test]
+fn serializes_person_ids_as_documented() {
+    let id = Uuid::new_v4();
+    let body = api::PersonBody {
+        id,
+        ..Default::default()
+    };
+
+    let as_json = serde_json::to_value(&body).unwrap();
+    let as_bson = mongodb::bson::to_document(&body).unwrap();
+
+    assert_eq!(as_json["id"], id.to_string());
+    assert_eq!(as_bson.get_str("id").unwrap(), id.to_string());
+}
+
+#[tokio::test]
+async fn serves_the_docs_page() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/docs", test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let policy = response.headers()["content-security-policy"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let page = response.text().await.unwrap();
+    assert!(page.contains("/openapi.json"));
+    // Assets are pinned to the exact version the policy allows.
+    assert!(!page.contains("swagger-ui-dist@5/"));
+    assert!(policy.contains("swagger-ui-dist@5.17.14/"));
+    assert!(page.contains("swagger-ui-dist@5.17.14/"));
+}
@@ -0,0 +1,135 @@
+use crate::helpers::{dev, post_dev};
+use axum::http::StatusCode as ReceiverStatus;
+use axum::routing::post;
+use axum::Json;
+use rinha_backend_2023_q3::configuration::OutboxSinkConfiguration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+async fn receive<T>(received: &mut mpsc::UnboundedReceiver<T>) -> T {
+    tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("no event published")
+        .unwrap()
+}
+
+/// An HTTP event receiver that fails the first `failures` requests.
+async fn spawn_http_receiver(
+    failures: usize,
+) -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let requests = Arc::new(AtomicUsize::new(0));
+    let app = axum::Router::new().route(
+        "/events",
+        post(move |Json(event): Json<serde_json::Value>| async move {
+            sender.send(event).unwrap();
+            if requests.fetch_add(1, Ordering::SeqCst) < failures {
+                ReceiverStatus::INTERNAL_SERVER_ERROR
+            } else {
+                ReceiverStatus::ACCEPTED
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/events", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, received)
+}
+
+/// A minimal NATS server accepting one client and forwarding the subject and payload of every
+/// `PUB` it gets.
+async fn spawn_nats_server() -> (String, mpsc::UnboundedReceiver<(String, serde_json::Value)>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        writer.write_all(b"INFO {}\r\n").await.unwrap();
+        let mut line = String::new();
+        while reader.read_line(&mut line).await.unwrap() > 0 {
+            let command: Vec<&str> = line.split_whitespace().collect();
+            match command.as_slice() {
+                ["PUB", subject, length] => {
+                    let mut payload = vec![0; length.parse::<usize>().unwrap() + 2];
+                    reader.read_exact(&mut payload).await.unwrap();
+                    let event = serde_json::from_slice(&payload[..payload.len() - 2]).unwrap();
+                    sender.send((subject.to_string(), event)).unwrap();
+                }
+                ["PING"] => writer.write_all(b"PONG\r\n").await.unwrap(),
+                _ => {}
+            }
+            line.clear();
+        }
+    });
+    (address, received)
+}
+
+#[tokio::test]
+async fn publishes_created_devs_over_http() {
+    let (url, mut received) = spawn_http_receiver(0).await;
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.outbox.sink = OutboxSinkConfiguration::Http { url };
+    })
+    .await;
+
+    let created: serde_json::Value = post_dev(&test_app.address, &dev("foo"))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let event = receive(&mut received).await;
+    assert_eq!(event["event"], "pessoa.criada");
+    assert_eq!(event["person"]["id"], created["id"]);
+}
+
+#[tokio::test]
+async fn republishes_events_the_sink_rejected() {
+    let (url, mut received) = spawn_http_receiver(1).await;
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.outbox.poll_interval_ms = 50;
+        config.outbox.sink = OutboxSinkConfiguration::Http { url };
+    })
+    .await;
+
+    post_dev(&test_app.address, &dev("foo")).await;
+
+    let rejected = receive(&mut received).await;
+    let accepted = receive(&mut received).await;
+    assert_eq!(rejected["id"], accepted["id"]);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(500), received.recv())
+            .await
+            .is_err(),
+        "accepted events must not be published again"
+    );
+}
+
+#[tokio::test]
+async fn publishes_created_devs_to_nats() {
+    let (address, mut received) = spawn_nats_server().await;
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.outbox.sink = OutboxSinkConfiguration::Nats {
+            address,
+            subject: String::from("rinha.pessoas"),
+        };
+    })
+    .await;
+
+    post_dev(&test_app.address, &dev("foo")).await;
+    post_dev(&test_app.address, &dev("bar")).await;
+
+    let mut nicknames = vec![];
+    for _ in 0..2 {
+        let (subject, event) = receive(&mut received).await;
+        assert_eq!(subject, "rinha.pessoas");
+        nicknames.push(event["person"]["apelido"].as_str().unwrap().to_string());
+    }
+    nicknames.sort();
+    assert_eq!(nicknames, vec!["bar", "foo"]);
+}
@@ -0,0 +1,100 @@
+use crate::helpers::{dev, post_dev};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::StatusCode;
+use rinha_backend_2023_q3::routes::person_stream;
+use std::time::Duration;
+
+/// Reads the event stream until `expected` shows up, failing after a few seconds.
+async fn read_until(response: &mut reqwest::Response, expected: &str) -> String {
+    let mut received = String::new();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !received.contains(expected) {
+            let chunk = response
+                .chunk()
+                .await
+                .expect("failed to read stream")
+                .expect("stream ended");
+            received.push_str(&String::from_utf8_lossy(&chunk));
+        }
+    })
+    .await
+    .unwrap_or_else(|_| panic!("{} not received, got {:?}", expected, received));
+    received
+}
+
+#[tokio::test]
+async fn streams_devs_created_after_subscribing() {
+    let test_app = crate::helpers::spawn_app().await;
+    let mut response = reqwest::Client::new()
+        .get(format!("{}/pessoas/stream", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(CONTENT_TYPE).unwrap(),
+        "text/event-stream"
+    );
+
+    let created: serde_json::Value = post_dev(&test_app.address, &dev("foo"))
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    let received = read_until(&mut response, "\"apelido\":\"foo\"").await;
+    assert!(received.contains("event: pessoa"));
+    assert!(received.contains(&format!("id: {}", created["id"].as_str().unwrap())));
+}
+
+#[tokio::test]
+async fn replays_devs_created_after_last_event_id() {
+    let test_app = crate::helpers::spawn_app().await;
+    let first: serde_json::Value = post_dev(&test_app.address, &dev("foo"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    post_dev(&test_app.address, &dev("bar")).await;
+
+    let mut response = reqwest::Client::new()
+        .get(format!("{}/pessoas/stream", &test_app.address))
+        .header("Last-Event-ID", first["id"].as_str().unwrap())
+        .send()
+        .await
+        .expect("failed request");
+
+    let received = read_until(&mut response, "\"apelido\":\"bar\"").await;
+    assert!(!received.contains("\"apelido\":\"foo\""));
+}
+
+#[tokio::test]
+async fn asks_to_resync_instead_of_replaying_too_many_devs() {
+    let test_app = crate::helpers::spawn_app().await;
+    let first: serde_json::Value = post_dev(&test_app.address, &dev("foo"))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let missed = (0..=person_stream::MAX_REPLAYED_PERSONS)
+        .map(|index| dev(&format!("dev{}", index)).to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+    reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", &test_app.address))
+        .header(CONTENT_TYPE, "application/x-ndjson")
+        .body(missed)
+        .send()
+        .await
+        .expect("failed request");
+
+    let mut response = reqwest::Client::new()
+        .get(format!("{}/pessoas/stream", &test_app.address))
+        .header("Last-Event-ID", first["id"].as_str().unwrap())
+        .send()
+        .await
+        .expect("failed request");
+
+    let received = read_until(&mut response, "event: resync").await;
+    assert!(!received.contains("event: pessoa"));
+}
@@ -145,3 +145,31 @@ async fn returns_422_unprocessable_entity_given_invalid_name() {
 
     assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
 }
+
+#[tokio::test]
+async fn returns_422_unprocessable_entity_given_a_taken_nickname() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+    let body = serde_json::json!({
+        "apelido": "foo",
+        "nome": "bar",
+        "nascimento": "1992-11-23",
+        "stack": ["Rust"]
+    });
+
+    let first = client
+        .post(format!("{}/pessoas", test_app.address))
+        .json(&body)
+        .send()
+        .await
+        .expect("failed request");
+    let second = client
+        .post(format!("{}/pessoas", test_app.address))
+        .json(&body)
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(first.status(), StatusCode::CREATED);
+    assert_eq!(second.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
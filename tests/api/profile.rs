@@ -0,0 +1,141 @@
+use crate::helpers::{dev_with, post_dev};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn stores_profile_fields() {
+    let test_app = crate::helpers::spawn_app().await;
+    let profile = serde_json::json!({
+        "email": "foo@example.com",
+        "localizacao": "Recife, PE",
+        "senioridade": "senior",
+        "github": "foo-bar",
+        "bio": "Writes Rust."
+    });
+
+    let created: serde_json::Value = post_dev(&test_app.address, &dev_with("foo", profile.clone()))
+        .await
+        .json()
+        .await
+        .unwrap();
+    let found: serde_json::Value = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas/{}",
+            test_app.address,
+            created["id"].as_str().unwrap()
+        ))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+
+    for (field, value) in profile.as_object().unwrap() {
+        assert_eq!(&found[field], value, "{}", field);
+    }
+}
+
+#[tokio::test]
+async fn omits_absent_profile_fields() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let created: serde_json::Value =
+        post_dev(&test_app.address, &dev_with("foo", serde_json::json!({})))
+            .await
+            .json()
+            .await
+            .unwrap();
+
+    let mut fields = created
+        .as_object()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<String>>();
+    fields.sort();
+    assert_eq!(fields, vec!["apelido", "id", "nascimento", "nome", "stack"]);
+}
+
+#[tokio::test]
+async fn returns_422_unprocessable_entity_given_invalid_profile_fields() {
+    let test_app = crate::helpers::spawn_app().await;
+    let invalid_profiles = [
+        serde_json::json!({"email": "foo"}),
+        serde_json::json!({"email": "foo@example"}),
+        serde_json::json!({"email": "foo bar@example.com"}),
+        serde_json::json!({"localizacao": " "}),
+        serde_json::json!({"localizacao": "x".repeat(101)}),
+        serde_json::json!({"senioridade": "ceo"}),
+        serde_json::json!({"github": "-foo"}),
+        serde_json::json!({"github": "foo--bar"}),
+        serde_json::json!({"github": "x".repeat(40)}),
+        serde_json::json!({"bio": "x".repeat(501)}),
+    ];
+
+    for profile in invalid_profiles {
+        let response = post_dev(&test_app.address, &dev_with("foo", profile.clone())).await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            profile
+        );
+    }
+}
+
+#[tokio::test]
+async fn reports_invalid_profile_fields_when_importing() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", test_app.address))
+        .body(dev_with("foo", serde_json::json!({"github": "@foo"})).to_string())
+        .send()
+        .await
+        .expect("failed request");
+
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["failed"], 1);
+    assert_eq!(report["lines"][0]["status"], 422);
+    assert!(report["lines"][0]["error"]
+        .as_str()
+        .unwrap()
+        .starts_with("github"));
+}
+
+#[tokio::test]
+async fn searches_by_location_and_seniority() {
+    let test_app = crate::helpers::spawn_app().await;
+    for (nickname, location, seniority) in [
+        ("foo", "Recife, PE", "senior"),
+        ("bar", "São Paulo, SP", "senior"),
+        ("baz", "recife", "junior"),
+    ] {
+        let mut dev = dev_with(
+            "foo",
+            serde_json::json!({
+                "localizacao": location,
+                "senioridade": seniority
+            }),
+        );
+        dev["apelido"] = serde_json::json!(nickname);
+        post_dev(&test_app.address, &dev).await;
+    }
+
+    let found: serde_json::Value = reqwest::Client::new()
+        .get(format!(
+            "{}/pessoas?location=RECIFE&seniority=senior",
+            test_app.address
+        ))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+
+    let found = found.as_array().unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["apelido"], "foo");
+}
@@ -0,0 +1,163 @@
+use crate::helpers::{dev_with, post_dev};
+use reqwest::StatusCode;
+use rinha_backend_2023_q3::configuration::SearchIndexConfiguration;
+
+async fn spawn_indexed_app() -> crate::helpers::TestApp {
+    let index_path = std::env::temp_dir().join(format!("search-index-{}", ulid::Ulid::new()));
+    crate::helpers::spawn_app_with(|static_config| {
+        static_config.search_index = Some(SearchIndexConfiguration {
+            path: index_path,
+            commit_interval_ms: 10,
+        });
+    })
+    .await
+}
+
+/// Searches once the index committed what was just stored.
+async fn search_nicknames(address: &str, query: &str) -> Vec<String> {
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let response = reqwest::Client::new()
+        .get(format!("{}/pessoas?{}", address, query))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .json::<Vec<std::collections::HashMap<String, serde_json::Value>>>()
+        .await
+        .unwrap()
+        .iter()
+        .map(|dev| dev["apelido"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_indexed_devs_matching_term() {
+    let test_app = spawn_indexed_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "foo",
+            serde_json::json!({"nome": "João", "nascimento": "1992-11-23", "stack": ["Rust"]}),
+        ),
+    )
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "bar",
+            serde_json::json!({"nome": "Ana", "nascimento": "1992-11-23", "stack": ["Python"]}),
+        ),
+    )
+    .await;
+
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=fo").await,
+        vec!["foo"]
+    );
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=joao").await,
+        vec!["foo"]
+    );
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=pyth").await,
+        vec!["bar"]
+    );
+    assert!(search_nicknames(&test_app.address, "t=ruby")
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_indexed_devs_composed_with_filters() {
+    let test_app = spawn_indexed_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "foo",
+            serde_json::json!({"nome": "Fulano", "nascimento": "1992-11-23", "stack": ["Rust"]}),
+        ),
+    )
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "bar",
+            serde_json::json!({"nome": "Fulana", "nascimento": "1992-11-23", "stack": ["Python"]}),
+        ),
+    )
+    .await;
+
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=fulan&stack=python").await,
+        vec!["bar"]
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_bulk_imported_devs_indexed() {
+    let test_app = spawn_indexed_app().await;
+    reqwest::Client::new()
+        .post(format!("{}/pessoas/bulk", test_app.address))
+        .body(
+            serde_json::json!({
+                "apelido": "baz",
+                "nome": "Conceição",
+                "nascimento": "1992-11-23"
+            })
+            .to_string(),
+        )
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=conceicao").await,
+        vec!["baz"]
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_indexed_devs_matching_stack_aliases() {
+    let test_app = spawn_indexed_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "foo",
+            serde_json::json!({"nome": "Fulano", "nascimento": "1992-11-23", "stack": ["Node.js"]}),
+        ),
+    )
+    .await;
+
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=nodejs").await,
+        vec!["foo"]
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_without_devs_only_matching_the_term_across_fields() {
+    let test_app = spawn_indexed_app().await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "foo",
+            serde_json::json!({"nome": "Rus", "nascimento": "1992-11-23", "stack": ["Gust"]}),
+        ),
+    )
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "bar",
+            serde_json::json!({"nome": "Ana", "nascimento": "1992-11-23", "stack": ["Rust"]}),
+        ),
+    )
+    .await;
+
+    assert_eq!(
+        search_nicknames(&test_app.address, "t=rust").await,
+        vec!["bar"]
+    );
+}
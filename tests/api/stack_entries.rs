@@ -0,0 +1,103 @@
+use crate::helpers::{dev_with, post_dev};
+use reqwest::StatusCode;
+
+async fn search(address: &str, query: &str) -> Vec<String> {
+    let found: Vec<serde_json::Value> = reqwest::get(format!("{}/pessoas?{}", address, query))
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    found
+        .iter()
+        .map(|dev| dev["apelido"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn accepts_stack_entries_alongside_names() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = post_dev(&test_app.address, &dev_with("foo", serde_json::json!({"stack": serde_json::json!(["Go", {"name": "Rust", "level": "expert", "years": 5}])})))
+    .await;
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(created["stack"], serde_json::json!(["Go", "Rust"]));
+    assert_eq!(
+        created["stack_detalhada"],
+        serde_json::json!([{"name": "Go"}, {"name": "Rust", "level": "expert", "years": 5}])
+    );
+}
+
+#[tokio::test]
+async fn omits_stack_details_for_plain_names() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let created: serde_json::Value = post_dev(
+        &test_app.address,
+        &dev_with(
+            "foo",
+            serde_json::json!({"stack": serde_json::json!(["Rust"])}),
+        ),
+    )
+    .await
+    .json()
+    .await
+    .unwrap();
+
+    assert!(created.get("stack_detalhada").is_none());
+}
+
+#[tokio::test]
+async fn filters_by_stack_level_and_years() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_dev(&test_app.address, &dev_with("expert", serde_json::json!({"stack": serde_json::json!([{"name": "Rust", "level": "expert", "years": 8}])})))
+    .await;
+    post_dev(&test_app.address, &dev_with("junior", serde_json::json!({"stack": serde_json::json!([{"name": "Rust", "level": "junior", "years": 1}, {"name": "Go", "level": "senior"}])})))
+    .await;
+    post_dev(
+        &test_app.address,
+        &dev_with(
+            "unrated",
+            serde_json::json!({"stack": serde_json::json!(["Rust"])}),
+        ),
+    )
+    .await;
+
+    assert_eq!(
+        search(&test_app.address, "stack=Rust&min_level=senior").await,
+        vec!["expert"]
+    );
+    let mut senior_anywhere = search(&test_app.address, "min_level=senior").await;
+    senior_anywhere.sort();
+    assert_eq!(senior_anywhere, vec!["expert", "junior"]);
+    assert_eq!(
+        search(&test_app.address, "stack=Rust&min_years=2").await,
+        vec!["expert"]
+    );
+}
+
+#[tokio::test]
+async fn returns_422_for_invalid_stack_entries() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    for stack in [
+        serde_json::json!([{"name": "Rust", "years": 200}]),
+        serde_json::json!([{"name": "Rust", "level": "wizard"}]),
+        serde_json::json!([{"level": "expert"}]),
+    ] {
+        let response = post_dev(
+            &test_app.address,
+            &dev_with("foo", serde_json::json!({"stack": stack.clone()})),
+        )
+        .await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            stack
+        );
+    }
+}
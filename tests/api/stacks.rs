@@ -0,0 +1,104 @@
+use crate::helpers::{dev_with, post_dev};
+use reqwest::StatusCode;
+
+async fn post_devs(address: &str) {
+    for (nickname, stacks) in [
+        ("foo", serde_json::json!(["Rust", "Python"])),
+        ("bar", serde_json::json!(["Rust", "Ruby"])),
+        ("baz", serde_json::json!(["Rust", "Ruby", "Go"])),
+    ] {
+        post_dev(
+            address,
+            &dev_with(
+                nickname,
+                serde_json::json!({"nome": "qux", "stack": stacks}),
+            ),
+        )
+        .await;
+    }
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_matching_stacks_given_a_prefix() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(&test_app.address).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/stacks?prefix=ru", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(
+        response_body,
+        serde_json::json!([
+            {"stack": "Rust", "count": 3},
+            {"stack": "Ruby", "count": 2}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_empty_list_given_a_prefix_without_matches() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(&test_app.address).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/stacks?prefix=c%2B%2B", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!([])
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_most_popular_stacks_limited() {
+    let test_app = crate::helpers::spawn_app().await;
+    post_devs(&test_app.address).await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/stacks/top?limit=2", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(
+        response_body,
+        serde_json::json!([
+            {"stack": "Rust", "count": 3},
+            {"stack": "Ruby", "count": 2}
+        ])
+    );
+}
+
+#[tokio::test]
+async fn returns_200_ok_with_aliased_stacks_counted_together() {
+    let test_app = crate::helpers::spawn_app().await;
+    for (nickname, stack) in [("foo", "node"), ("bar", "NodeJS"), ("baz", "Node.js")] {
+        post_dev(
+            &test_app.address,
+            &dev_with(nickname, serde_json::json!({"stack": [stack]})),
+        )
+        .await;
+    }
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/stacks?prefix=NODE", &test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_body = response.json::<Vec<serde_json::Value>>().await.unwrap();
+    assert_eq!(response_body.len(), 1);
+    assert_eq!(response_body[0]["count"], 3);
+}
@@ -0,0 +1,168 @@
+use reqwest::StatusCode;
+
+use crate::helpers::{dev, post_dev, post_dev_with_headers};
+use rinha_backend_2023_q3::configuration::{
+    self, TenancyConfiguration, TenantConfiguration, TenantResolution,
+};
+use rinha_backend_2023_q3::tenancy;
+
+fn tenancy_configuration(resolve_from: TenantResolution) -> TenancyConfiguration {
+    TenancyConfiguration {
+        resolve_from,
+        tenants: ["acme", "globex"]
+            .into_iter()
+            .map(|id| TenantConfiguration {
+                id: id.to_string(),
+                database_name: format!("test-{}", ulid::Ulid::new()),
+                collection_name: None,
+            })
+            .collect(),
+    }
+}
+
+fn by_header() -> TenantResolution {
+    TenantResolution::Header {
+        name: String::from("x-tenant-id"),
+    }
+}
+
+#[test]
+fn finds_subdomains() {
+    assert_eq!(tenancy::subdomain("acme.api.example.com"), Some("acme"));
+    assert_eq!(tenancy::subdomain("acme.localhost:3000"), Some("acme"));
+    assert_eq!(tenancy::subdomain("localhost:3000"), None);
+    assert_eq!(tenancy::subdomain(".example.com"), None);
+}
+
+#[test]
+fn requires_a_known_tenant_exactly_when_configured() {
+    let mut static_config = configuration::get_static_configuration().unwrap();
+    assert!(static_config.database_for(None).is_ok());
+    assert!(static_config.database_for(Some("acme")).is_err());
+
+    static_config.tenancy = Some(tenancy_configuration(by_header()));
+    assert!(static_config.database_for(None).is_err());
+    assert!(static_config.database_for(Some("initech")).is_err());
+    let acme = static_config.database_for(Some("acme")).unwrap();
+    assert!(acme.database_name.starts_with("test-"));
+    assert_eq!(acme.collection_name, static_config.database.collection_name);
+}
+
+#[test]
+fn rejects_tenants_sharing_a_database_or_an_id() {
+    let mut sharing_a_database = tenancy_configuration(by_header());
+    assert!(sharing_a_database.validate().is_ok());
+    sharing_a_database.tenants[1].database_name =
+        sharing_a_database.tenants[0].database_name.clone();
+    let mut sharing_an_id = tenancy_configuration(by_header());
+    sharing_an_id.tenants[1].id = sharing_an_id.tenants[0].id.to_uppercase();
+
+    assert!(sharing_a_database.validate().is_err());
+    assert!(sharing_an_id.validate().is_err());
+}
+
+#[test]
+fn lowercases_tenant_ids() {
+    let mut tenancy = tenancy_configuration(by_header());
+    tenancy.tenants[0].id = String::from("Acme");
+
+    assert!(tenancy.validate().is_ok());
+    assert_eq!(tenancy.tenants[0].id, "acme");
+}
+
+#[tokio::test]
+#[should_panic(expected = "invalid tenancy configuration")]
+async fn refuses_to_serve_tenants_sharing_a_database() {
+    crate::helpers::spawn_app_with(|config| {
+        let mut tenancy = tenancy_configuration(by_header());
+        tenancy.tenants[1].database_name = tenancy.tenants[0].database_name.clone();
+        config.tenancy = Some(tenancy);
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn routes_only_requests_naming_a_known_tenant() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.tenancy = Some(tenancy_configuration(by_header()));
+    })
+    .await;
+    // Invalid, so that reaching a tenant answers 422 without touching its database.
+    let invalid = serde_json::json!({"apelido": 1});
+
+    let health = reqwest::get(format!("{}/health-check", test_app.address))
+        .await
+        .expect("failed request");
+    let anonymous = post_dev(&test_app.address, &invalid).await;
+    let unknown =
+        post_dev_with_headers(&test_app.address, &invalid, &[("x-tenant-id", "initech")]).await;
+    let known =
+        post_dev_with_headers(&test_app.address, &invalid, &[("x-tenant-id", "acme")]).await;
+
+    assert_eq!(health.status(), StatusCode::OK);
+    assert_eq!(anonymous.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    assert_eq!(known.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn resolves_tenants_from_subdomains() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.tenancy = Some(tenancy_configuration(TenantResolution::Subdomain));
+    })
+    .await;
+    let invalid = serde_json::json!({"apelido": 1});
+
+    let known =
+        post_dev_with_headers(&test_app.address, &invalid, &[("host", "globex.localhost")]).await;
+    let known_in_capitals =
+        post_dev_with_headers(&test_app.address, &invalid, &[("host", "GloBex.localhost")]).await;
+    let unknown = post_dev_with_headers(
+        &test_app.address,
+        &invalid,
+        &[("host", "initech.localhost")],
+    )
+    .await;
+
+    assert_eq!(known.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(known_in_capitals.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn keeps_tenants_apart() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.tenancy = Some(tenancy_configuration(by_header()));
+    })
+    .await;
+    let created: serde_json::Value =
+        post_dev_with_headers(&test_app.address, &dev("foo"), &[("x-tenant-id", "acme")])
+            .await
+            .json()
+            .await
+            .unwrap();
+    let get_as = |tenant: &str, path: String| {
+        reqwest::Client::new()
+            .get(format!("{}{}", test_app.address, path))
+            .header("x-tenant-id", tenant)
+            .send()
+    };
+    let path = format!("/pessoas/{}", created["id"].as_str().unwrap());
+
+    let from_acme = get_as("acme", path.clone()).await.expect("failed request");
+    let from_globex = get_as("globex", path).await.expect("failed request");
+    // The nickname is only taken within a tenant.
+    let created_in_globex =
+        post_dev_with_headers(&test_app.address, &dev("foo"), &[("x-tenant-id", "globex")]).await;
+    let acme_count = get_as("acme", String::from("/contagem-pessoas"))
+        .await
+        .expect("failed request")
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(from_acme.status(), StatusCode::OK);
+    assert_eq!(from_globex.status(), StatusCode::NOT_FOUND);
+    assert_eq!(created_in_globex.status(), StatusCode::CREATED);
+    assert_eq!(acme_count, "1");
+}
@@ -0,0 +1,269 @@
+use crate::helpers::dev;
+use reqwest::header::LOCATION;
+use reqwest::StatusCode;
+
+async fn assert_error(response: reqwest::Response, status: StatusCode, code: &str) {
+    assert_eq!(response.status(), status);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], code);
+    assert!(body["error"]["message"].is_string());
+}
+
+#[tokio::test]
+async fn mirrors_the_contest_routes_under_v1() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let post_response = client
+        .post(format!("{}/v1/pessoas", test_app.address))
+        .json(&dev("foo"))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(post_response.status(), StatusCode::CREATED);
+    let location = post_response.headers()[LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let created: serde_json::Value = post_response.json().await.unwrap();
+
+    for path in [location.clone(), format!("/v1{}", location)] {
+        let found: serde_json::Value = client
+            .get(format!("{}{}", test_app.address, path))
+            .send()
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(found, created);
+    }
+}
+
+#[tokio::test]
+async fn rejects_invalid_v1_bodies_like_the_contest_routes() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/v1/pessoas", test_app.address))
+        .json(&serde_json::json!({"apelido": "foo"}))
+        .send()
+        .await
+        .expect("failed request");
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn wraps_v2_persons_in_an_envelope() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let post_response = client
+        .post(format!("{}/v2/pessoas", test_app.address))
+        .json(&dev("foo"))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(post_response.status(), StatusCode::CREATED);
+    let location = post_response.headers()[LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let created: serde_json::Value = post_response.json().await.unwrap();
+    assert_eq!(created["data"]["apelido"], "foo");
+    assert!(created.get("meta").is_none());
+    assert_eq!(
+        location,
+        format!("/v2/pessoas/{}", created["data"]["id"].as_str().unwrap())
+    );
+
+    let found: serde_json::Value = client
+        .get(format!("{}{}", test_app.address, location))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(found, created);
+}
+
+#[tokio::test]
+async fn paginates_v2_searches() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+    for nickname in ["foo1", "foo2", "foo3"] {
+        client
+            .post(format!("{}/v2/pessoas", test_app.address))
+            .json(&dev(nickname))
+            .send()
+            .await
+            .expect("failed request");
+    }
+
+    let mut nicknames = vec![];
+    for page in [1, 2] {
+        let found: serde_json::Value = client
+            .get(format!(
+                "{}/v2/pessoas?t=foo&sort=nickname&per_page=2&page={}",
+                test_app.address, page
+            ))
+            .send()
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            found["meta"],
+            serde_json::json!({"page": page, "per_page": 2, "total": 3})
+        );
+        for dev in found["data"].as_array().unwrap() {
+            nicknames.push(dev["apelido"].as_str().unwrap().to_string());
+        }
+    }
+
+    assert_eq!(nicknames, vec!["foo1", "foo2", "foo3"]);
+}
+
+async fn collect_pages(address: &str, query: &str) -> Vec<String> {
+    let mut nicknames = vec![];
+    for page in [1, 2] {
+        let found: serde_json::Value = reqwest::Client::new()
+            .get(format!(
+                "{}/v2/pessoas?{}&per_page=2&page={}",
+                address, query, page
+            ))
+            .send()
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(found["meta"]["total"], 3);
+        for dev in found["data"].as_array().unwrap() {
+            nicknames.push(dev["apelido"].as_str().unwrap().to_string());
+        }
+    }
+    nicknames
+}
+
+#[tokio::test]
+async fn paginates_v2_searches_by_relevance_and_without_a_term() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+    for nickname in ["xfoo", "foobar", "foo"] {
+        client
+            .post(format!("{}/v2/pessoas", test_app.address))
+            .json(&dev(nickname))
+            .send()
+            .await
+            .expect("failed request");
+    }
+
+    let ranked = collect_pages(&test_app.address, "t=foo").await;
+    let mut by_stack = collect_pages(&test_app.address, "stack=Rust").await;
+    by_stack.sort();
+
+    assert_eq!(ranked, vec!["foo", "foobar", "xfoo"]);
+    assert_eq!(by_stack, vec!["foo", "foobar", "xfoo"]);
+}
+
+#[tokio::test]
+async fn describes_v2_storage_errors() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+    let created: serde_json::Value = client
+        .post(format!("{}/v2/pessoas", test_app.address))
+        .json(&dev("foo"))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    let id = created["data"]["id"].as_str().unwrap();
+
+    let taken = client
+        .post(format!("{}/v2/pessoas", test_app.address))
+        .json(&dev("foo"))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(taken, StatusCode::UNPROCESSABLE_ENTITY, "nickname_taken").await;
+
+    let deleted = client
+        .delete(format!("{}/v2/pessoas/{}", test_app.address, id))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    let missing = client
+        .get(format!("{}/v2/pessoas/{}", test_app.address, id))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(missing, StatusCode::NOT_FOUND, "person_not_found").await;
+}
+
+#[tokio::test]
+async fn describes_v2_request_errors() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let invalid_id = client
+        .get(format!("{}/v2/pessoas/not-a-uuid", test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(invalid_id, StatusCode::BAD_REQUEST, "invalid_path").await;
+
+    let invalid_body = client
+        .post(format!("{}/v2/pessoas", test_app.address))
+        .json(&serde_json::json!({"apelido": "foo"}))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(
+        invalid_body,
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "invalid_body",
+    )
+    .await;
+
+    let invalid_key = client
+        .post(format!("{}/v2/pessoas", test_app.address))
+        .header("idempotency-key", "")
+        .json(&dev("foo"))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(
+        invalid_key,
+        StatusCode::BAD_REQUEST,
+        "invalid_idempotency_key",
+    )
+    .await;
+
+    let invalid_page = client
+        .get(format!("{}/v2/pessoas?t=foo&page=0", test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(invalid_page, StatusCode::BAD_REQUEST, "invalid_pagination").await;
+
+    let missing_criteria = client
+        .get(format!("{}/v2/pessoas", test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(missing_criteria, StatusCode::BAD_REQUEST, "invalid_search").await;
+
+    let invalid_sort = client
+        .get(format!("{}/v2/pessoas?t=foo&sort=age", test_app.address))
+        .send()
+        .await
+        .expect("failed request");
+    assert_error(invalid_sort, StatusCode::BAD_REQUEST, "invalid_query").await;
+}
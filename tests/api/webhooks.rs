@@ -0,0 +1,304 @@
+use crate::helpers::{dev, post_dev};
+use axum::body::Bytes;
+use axum::http::{HeaderMap, StatusCode as ReceiverStatus};
+use axum::routing::post;
+use reqwest::StatusCode;
+use rinha_backend_2023_q3::configuration;
+use rinha_backend_2023_q3::startup::get_database_connection;
+use rinha_backend_2023_q3::structs::api;
+use rinha_backend_2023_q3::webhooks;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// A local HTTP server recording the deliveries it gets, answering them with `statuses` in
+/// order and then with 200.
+async fn spawn_receiver(
+    statuses: Vec<ReceiverStatus>,
+) -> (String, mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) {
+    let (sender, received) = mpsc::unbounded_channel();
+    let statuses = Arc::new(Mutex::new(VecDeque::from(statuses)));
+    let app = axum::Router::new().route(
+        "/hook",
+        post(move |headers: HeaderMap, body: Bytes| async move {
+            sender.send((headers, body)).unwrap();
+            statuses
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or(ReceiverStatus::OK)
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (url, received)
+}
+
+async fn receive(received: &mut mpsc::UnboundedReceiver<(HeaderMap, Bytes)>) -> (HeaderMap, Bytes) {
+    tokio::time::timeout(Duration::from_secs(5), received.recv())
+        .await
+        .expect("no delivery received")
+        .unwrap()
+}
+
+async fn create_webhook(address: &str, url: &str, secret: &str) -> serde_json::Value {
+    let response = reqwest::Client::new()
+        .post(format!("{}/webhooks", address))
+        .json(&serde_json::json!({"url": url, "secret": secret}))
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    response.json().await.unwrap()
+}
+
+/// Polls a list of `webhook_id`, `deliveries` or `dead-letters`, until it has `expected` entries.
+async fn wait_for(
+    address: &str,
+    webhook_id: &str,
+    list: &str,
+    expected: usize,
+) -> Vec<serde_json::Value> {
+    let url = format!("{}/webhooks/{}/{}", address, webhook_id, list);
+    for _ in 0..50 {
+        let logs: Vec<serde_json::Value> = reqwest::get(&url)
+            .await
+            .expect("failed request")
+            .json()
+            .await
+            .unwrap();
+        if logs.len() >= expected {
+            return logs;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("{} {} not recorded", expected, list);
+}
+
+#[tokio::test]
+async fn manages_webhooks() {
+    let test_app = crate::helpers::spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let created: serde_json::Value = client
+        .post(format!("{}/webhooks", &test_app.address))
+        .json(&serde_json::json!({"url": "http://localhost:1/hook"}))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(created["secret"].as_str().unwrap().len(), 64);
+    let webhook_url = format!(
+        "{}/webhooks/{}",
+        &test_app.address,
+        created["id"].as_str().unwrap()
+    );
+
+    let listed: Vec<serde_json::Value> = client
+        .get(format!("{}/webhooks", &test_app.address))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert!(listed[0].get("secret").is_none());
+
+    let updated: serde_json::Value = client
+        .put(&webhook_url)
+        .json(&serde_json::json!({"url": "https://example.com/hook"}))
+        .send()
+        .await
+        .expect("failed request")
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(updated["url"], "https://example.com/hook");
+
+    let deleted = client
+        .delete(&webhook_url)
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(deleted.status(), StatusCode::NO_CONTENT);
+    let found = client
+        .get(&webhook_url)
+        .send()
+        .await
+        .expect("failed request");
+    assert_eq!(found.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn rejects_invalid_webhook_urls() {
+    let test_app = crate::helpers::spawn_app().await;
+
+    for url in ["not a url", "ftp://example.com/hook", "http://"] {
+        let response = reqwest::Client::new()
+            .post(format!("{}/webhooks", &test_app.address))
+            .json(&serde_json::json!({"url": url}))
+            .send()
+            .await
+            .expect("failed request");
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            url
+        );
+    }
+}
+
+#[tokio::test]
+async fn rejects_private_webhook_hosts_unless_allowed() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.webhooks.allow_private_hosts = false;
+    })
+    .await;
+
+    for url in [
+        "http://localhost/hook",
+        "http://127.0.0.1:8080/hook",
+        "http://10.0.0.1/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/hook",
+        "http://[::ffff:192.168.0.1]/hook",
+    ] {
+        let response = reqwest::Client::new()
+            .post(format!("{}/webhooks", &test_app.address))
+            .json(&serde_json::json!({"url": url}))
+            .send()
+            .await
+            .expect("failed request");
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            url
+        );
+    }
+    assert!(webhooks::is_allowed_url("https://example.com/hook", false));
+    assert!(webhooks::is_allowed_url("http://127.0.0.1/hook", true));
+}
+
+#[tokio::test]
+async fn delivers_signed_payloads_for_created_devs() {
+    let test_app = crate::helpers::spawn_app().await;
+    let (url, mut received) = spawn_receiver(vec![]).await;
+    create_webhook(&test_app.address, &url, "s3cr3t").await;
+
+    post_dev(&test_app.address, &dev("foo")).await;
+
+    let (headers, body) = receive(&mut received).await;
+    assert_eq!(
+        headers.get(webhooks::SIGNATURE_HEADER).unwrap(),
+        webhooks::sign("s3cr3t", &body).as_str()
+    );
+    assert_eq!(
+        headers.get(webhooks::EVENT_HEADER).unwrap(),
+        "pessoa.criada"
+    );
+    let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload["event"], "pessoa.criada");
+    assert_eq!(payload["person"]["apelido"], "foo");
+}
+
+#[tokio::test]
+async fn retries_failed_deliveries() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.webhooks.initial_backoff_ms = 10;
+    })
+    .await;
+    let (url, mut received) = spawn_receiver(vec![ReceiverStatus::INTERNAL_SERVER_ERROR]).await;
+    let webhook = create_webhook(&test_app.address, &url, "s3cr3t").await;
+
+    post_dev(&test_app.address, &dev("foo")).await;
+
+    let (first_headers, _) = receive(&mut received).await;
+    let (second_headers, _) = receive(&mut received).await;
+    assert_eq!(
+        first_headers.get(webhooks::DELIVERY_HEADER),
+        second_headers.get(webhooks::DELIVERY_HEADER)
+    );
+    let logs = wait_for(
+        &test_app.address,
+        webhook["id"].as_str().unwrap(),
+        "deliveries",
+        2,
+    )
+    .await;
+    assert_eq!(logs[0]["attempt"], 2);
+    assert_eq!(logs[0]["status"], 200);
+    assert_eq!(logs[1]["attempt"], 1);
+    assert_eq!(logs[1]["status"], 500);
+}
+
+#[tokio::test]
+async fn dead_letters_deliveries_after_max_attempts() {
+    let test_app = crate::helpers::spawn_app_with(|config| {
+        config.webhooks.initial_backoff_ms = 10;
+        config.webhooks.max_attempts = 2;
+    })
+    .await;
+    let (url, mut received) = spawn_receiver(vec![
+        ReceiverStatus::INTERNAL_SERVER_ERROR,
+        ReceiverStatus::SERVICE_UNAVAILABLE,
+    ])
+    .await;
+    let webhook = create_webhook(&test_app.address, &url, "s3cr3t").await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    post_dev(&test_app.address, &dev("foo")).await;
+
+    receive(&mut received).await;
+    receive(&mut received).await;
+    let dead_letters = wait_for(&test_app.address, webhook_id, "dead-letters", 1).await;
+    assert_eq!(dead_letters[0]["attempts"], 2);
+    let logs = wait_for(&test_app.address, webhook_id, "deliveries", 2).await;
+    assert!(logs.iter().all(|log| log["status"] != 200));
+}
+
+#[tokio::test]
+async fn delivers_to_webhooks_created_after_earlier_devs() {
+    let test_app = crate::helpers::spawn_app().await;
+    let (url, mut received) = spawn_receiver(vec![]).await;
+    // Caches that nobody is subscribed yet.
+    post_dev(&test_app.address, &dev("foo")).await;
+    create_webhook(&test_app.address, &url, "s3cr3t").await;
+
+    post_dev(&test_app.address, &dev("bar")).await;
+
+    let (_, body) = receive(&mut received).await;
+    let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload["person"]["apelido"], "bar");
+}
+
+#[tokio::test]
+async fn queues_a_relayed_event_once() {
+    let test_app = crate::helpers::spawn_app().await;
+    let (url, _received) = spawn_receiver(vec![ReceiverStatus::INTERNAL_SERVER_ERROR; 10]).await;
+    create_webhook(&test_app.address, &url, "s3cr3t").await;
+    let mut static_config = configuration::get_static_configuration().unwrap();
+    static_config.database.database_name = test_app.database_name.clone();
+    let queue = webhooks::WebhookQueue::new(
+        get_database_connection(static_config.database)
+            .await
+            .expect("failed to connect to mongodb"),
+        &static_config.webhooks,
+    );
+    let event = api::PersonEventBody {
+        id: Uuid::new_v4(),
+        event: "pessoa.criada".to_string(),
+        occurred_at: chrono::Utc::now(),
+        person: api::PersonBody::default(),
+    };
+
+    assert_eq!(queue.enqueue(&event).await.unwrap(), 1);
+    assert_eq!(queue.enqueue(&event).await.unwrap(), 0);
+}